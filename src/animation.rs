@@ -0,0 +1,73 @@
+use crate::board::Board;
+use crate::piece::Piece;
+
+/* pieces sliding from where they were before a move to where they are after it */
+pub struct Animation {
+  /* piece, from, to */
+  pub moving: Vec<(Piece, usize, usize)>,
+  /* taken pieces fade out where they stood */
+  pub captured: Vec<(Piece, usize)>,
+  elapsed: f64,
+  duration: f64
+}
+
+fn same_piece(a: Option<Piece>, b: Option<Piece>) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => a.color == b.color && a.piece == b.piece,
+    (None, None) => true,
+    _ => false
+  }
+}
+
+impl Animation {
+  /* worked out from the difference between the two positions, so a rook that castles, a pawn taken
+     en passant or a move taken back all animate without knowing about the move itself */
+  pub fn between(before: &Board, after: &Board, duration: f64) -> Option<Self> {
+    let mut vanished = Vec::new();
+    let mut appeared = Vec::new();
+
+    for idx in 0 .. 8 * 8 {
+      let (old, new) = (before.board[idx].piece, after.board[idx].piece);
+      if same_piece(old, new) { continue; }
+
+      if let Some(old) = old { vanished.push((old, idx)); }
+      if let Some(new) = new { appeared.push((new, idx)); }
+    }
+
+    let mut moving = Vec::new();
+
+    for (piece, to) in appeared {
+      /* the same kind of piece, otherwise a pawn that just promoted */
+      let found = vanished.iter().position(|(old, _)| old.color == piece.color && old.piece == piece.piece)
+        .or_else(|| vanished.iter().position(|(old, _)| old.color == piece.color));
+
+      if let Some(i) = found {
+        let (old, from) = vanished.remove(i);
+        moving.push((old, from, to));
+      }
+    }
+
+    if moving.is_empty() { return None; }
+
+    Some(Animation { moving, captured: vanished, elapsed: 0.0, duration })
+  }
+
+  /* true once it's done */
+  pub fn tick(&mut self, dt: f64) -> bool {
+    self.elapsed += dt;
+
+    self.elapsed >= self.duration
+  }
+
+  /* 0 to 1, eased out so pieces slow down as they land */
+  pub fn progress(&self) -> f64 {
+    let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+
+    1.0 - (1.0 - t).powi(3)
+  }
+
+  /* squares whose piece is drawn by the animation instead of the board */
+  pub fn hides(&self, idx: usize) -> bool {
+    self.moving.iter().any(|(_, _, to)| *to == idx)
+  }
+}
@@ -0,0 +1,132 @@
+use crate::board::{ parse_square, square_name };
+
+/* the colors lichess and chess.com use, in pgn as their first letter */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MarkColor {
+  Green,
+  Red,
+  Yellow,
+  Blue
+}
+
+/* arrows and highlighted squares drawn on one position */
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+  pub arrows: Vec<(MarkColor, usize, usize)>,
+  pub squares: Vec<(MarkColor, usize)>
+}
+
+impl MarkColor {
+  pub fn letter(self) -> char {
+    match self {
+      MarkColor::Green => 'G',
+      MarkColor::Red => 'R',
+      MarkColor::Yellow => 'Y',
+      MarkColor::Blue => 'B'
+    }
+  }
+
+  pub fn from_letter(c: char) -> Option<Self> {
+    match c {
+      'G' => Some(MarkColor::Green),
+      'R' => Some(MarkColor::Red),
+      'Y' => Some(MarkColor::Yellow),
+      'B' => Some(MarkColor::Blue),
+      _ => None
+    }
+  }
+}
+
+/* the shaft and the head of an arrow between two square centers, two polygons that don't overlap
+   so the color stays even where they meet */
+pub fn arrow_polygons(start: [f64; 2], end: [f64; 2], square: f64) -> ([[f64; 2]; 4], [[f64; 2]; 3]) {
+  let length = (end[0] - start[0]).hypot(end[1] - start[1]);
+
+  /* along the arrow and across it */
+  let (dx, dy) = ((end[0] - start[0]) / length, (end[1] - start[1]) / length);
+  let (nx, ny) = (-dy, dx);
+
+  let (shaft, head, head_length) = (square * 0.09, square * 0.25, square * 0.4);
+  let neck = [end[0] - dx * head_length, end[1] - dy * head_length];
+
+  ([
+    [start[0] + nx * shaft, start[1] + ny * shaft],
+    [neck[0] + nx * shaft, neck[1] + ny * shaft],
+    [neck[0] - nx * shaft, neck[1] - ny * shaft],
+    [start[0] - nx * shaft, start[1] - ny * shaft]
+  ], [
+    [neck[0] + nx * head, neck[1] + ny * head],
+    end,
+    [neck[0] - nx * head, neck[1] - ny * head]
+  ])
+}
+
+impl Annotations {
+  pub fn is_empty(&self) -> bool {
+    self.arrows.is_empty() && self.squares.is_empty()
+  }
+
+  /* drawing the same mark again removes it, drawing it in another color recolors it */
+  pub fn toggle_arrow(&mut self, color: MarkColor, from: usize, to: usize) {
+    match self.arrows.iter().position(|(_, f, t)| *f == from && *t == to) {
+      Some(i) if self.arrows[i].0 == color => { self.arrows.remove(i); }
+      Some(i) => self.arrows[i].0 = color,
+      None => self.arrows.push((color, from, to))
+    }
+  }
+
+  pub fn toggle_square(&mut self, color: MarkColor, idx: usize) {
+    match self.squares.iter().position(|(_, square)| *square == idx) {
+      Some(i) if self.squares[i].0 == color => { self.squares.remove(i); }
+      Some(i) => self.squares[i].0 = color,
+      None => self.squares.push((color, idx))
+    }
+  }
+
+  /* [%csl Ge4,Rd5][%cal Ge2e4], the commands that go in a pgn comment */
+  pub fn to_comment(&self) -> String {
+    let mut comment = String::new();
+
+    if !self.squares.is_empty() {
+      let squares = self.squares.iter().map(|(color, idx)| format!("{}{}", color.letter(), square_name(*idx)));
+      comment.push_str(&format!("[%csl {}]", squares.collect::<Vec<_>>().join(",")));
+    }
+
+    if !self.arrows.is_empty() {
+      let arrows = self.arrows.iter().map(|(color, from, to)| format!("{}{}{}", color.letter(), square_name(*from), square_name(*to)));
+      comment.push_str(&format!("[%cal {}]", arrows.collect::<Vec<_>>().join(",")));
+    }
+
+    comment
+  }
+
+  /* picks the [%csl] and [%cal] commands out of a pgn comment, anything else is ignored */
+  pub fn parse_comment(&mut self, comment: &str) {
+    let mut rest = comment;
+
+    while let Some(start) = rest.find("[%") {
+      let end = match rest[start ..].find(']') {
+        Some(end) => start + end,
+        None => break
+      };
+
+      let mut command = rest[start + 2 .. end].split_whitespace();
+      let (name, args) = (command.next().unwrap_or(""), command.next().unwrap_or(""));
+
+      for mark in args.split(',') {
+        let color = match mark.chars().next().and_then(MarkColor::from_letter) {
+          Some(color) => color,
+          None => continue
+        };
+
+        match (name, mark.get(1 .. 3).and_then(parse_square), mark.get(3 .. 5).and_then(parse_square)) {
+          ("csl", Some(idx), None) => self.toggle_square(color, idx),
+          ("cal", Some(from), Some(to)) => self.toggle_arrow(color, from, to),
+          _ => {}
+        }
+      }
+
+      rest = &rest[end + 1 ..];
+    }
+  }
+}
@@ -0,0 +1,16 @@
+use std::env;
+use std::path::{ Path, PathBuf };
+
+/* built in so text can always be drawn */
+pub const FONT: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+
+/* assets next to the executable win, then the source tree for cargo run builds in target/<profile>,
+   then the working directory, which is also what's returned when nothing exists yet */
+pub fn asset_path(name: &str) -> PathBuf {
+  let relative = Path::new("assets").join(name);
+
+  let exe_dir = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+  let candidates = exe_dir.iter().flat_map(|dir| [dir.join(&relative), dir.join("../..").join(&relative)]);
+
+  candidates.chain([relative.clone()]).find(|path| path.exists()).unwrap_or(relative)
+}
@@ -1,19 +1,20 @@
-use std::fmt::Debug;
+use std::fmt::{ self, Debug, Display };
 
 use crate::cursor::MouseHandler;
 use crate::piece::{Piece, Color, PieceType};
 
-use crate::{ PIECE_WIDTH, PIECE_HEIGHT };
+use crate::layout::Layout;
 
 use crate::move_generator::*;
 
-const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-const DEFAULT_BOARD: [Tile; 8 * 8] = [Tile { piece: None, selected: false }; 8 * 8];
+pub const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/* K Q k q, the king starts on 39 and 32 */
+const CASTLING_ROOKS: [(char, usize); 4] = [('K', 63), ('Q', 7), ('k', 56), ('q', 0)];
+const DEFAULT_BOARD: [Tile; 8 * 8] = [Tile { piece: None }; 8 * 8];
 
 #[derive(Clone, Copy)]
 pub struct Tile {
   pub piece: Option<Piece>,
-  pub selected: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -26,15 +27,22 @@ pub struct SelectedPiece {
   pub dest: Option<usize>
 }
 
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum GameResult {
+  Win(Color),
+  Draw
+}
+
+#[derive(Clone)]
 pub struct Board {
   pub board: [Tile; 8 * 8],
   pub current_select: Option<SelectedPiece>,
   pub color_to_move: Color,
   pub moves: Vec<Move>,
   pub last_move: Option<Move>,
+  pub ply: usize,
   stack: Vec<(usize, Option<Piece>)>,
-  old_stack: Vec<(usize, Option<Piece>)>,
-  selected: Vec<usize>
+  old_stack: Vec<(usize, Option<Piece>)>
 }
 
 impl Debug for Tile {
@@ -66,6 +74,43 @@ impl Debug for Board {
   }
 }
 
+/* the position as white sees it with labels, side to move, castling and en passant under it,
+   {:#} draws unicode pieces instead of fen letters */
+impl Display for Board {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for rank in 0 .. 8 {
+      write!(f, "{} |", 8 - rank)?;
+
+      for file in 0 .. 8 {
+        let square = match self.get_piece_at(file * 8 + rank) {
+          Some(piece) if f.alternate() => piece.to_unicode(),
+          Some(piece) => piece.to_fen(),
+          None if f.alternate() => '·',
+          None => '.'
+        };
+
+        write!(f, " {}", square)?;
+      }
+
+      writeln!(f)?;
+    }
+
+    writeln!(f, "  +----------------")?;
+    writeln!(f, "    a b c d e f g h")?;
+
+    let en_passant = self.en_passant_square().map_or(String::from("-"), square_name);
+    let to_move = if self.color_to_move == Color::White { "White" } else { "Black" };
+
+    write!(f, "{} to move, castling {}, en passant {}", to_move, self.castling_field(), en_passant)
+  }
+}
+
+impl Default for Board {
+  fn default() -> Self {
+    Board::new()
+  }
+}
+
 impl Board {
   pub fn new() -> Self {
     return Board::from_fen(DEFAULT_FEN);
@@ -98,9 +143,7 @@ impl Board {
   }
 
   pub fn try_move(&mut self, select: SelectedPiece) {
-    self.unhighlight_tiles();
-
-    let mut piece = select.piece;
+    let piece = select.piece;
     //let last_last_move = self.last_move;
     let dest = select.dest.unwrap();
     let origin = select.origin;
@@ -122,26 +165,8 @@ impl Board {
       let mov = mov.clone();
 
       if mov.start == origin && mov.target == dest {
-
-        self.push_move(origin, None);
-
-        match mov.special {
-          SpecialMove::EnPassant(target_pawn) => {
-            self.push_move(dest, Some(piece));
-            self.push_move(target_pawn, None);
-          }
-
-          SpecialMove::Promotion => {
-            self.push_move(dest, Some(Piece { color: piece.color, piece: PieceType::Queen, moved: true }));
-          }
-
-          SpecialMove::None => {
-            self.push_move(dest, Some(piece));
-          }
-        }
-
-        self.last_move = Some(mov);
-        self.commit_moves();
+        self.board[origin].piece = Some(piece);
+        self.make_move(mov);
 
         /*generate_all_moves(self);
 
@@ -157,13 +182,7 @@ impl Board {
           }
         }*/
 
-        self.highlight_tile(origin);
-        self.highlight_tile(dest);
-
-        self.color_to_move.reverse();
-        piece.moved = true;
-
-        println!("{:?}", self);
+        log::debug!(target: "rules", "{}{} played\n{}", square_name(origin), square_name(dest), self);
 
         self.moves.clear();
         return
@@ -175,14 +194,77 @@ impl Board {
 
 
 
-  pub fn select(&mut self, mouse: &mut MouseHandler) {
+  /* applies a move without any checks, used by try_move and the search */
+  pub fn make_move(&mut self, mov: Move) {
+    let mut piece = self.board[mov.start].piece.unwrap();
+    piece.moved = true;
+
+    self.push_move(mov.start, None);
+
+    match mov.special {
+      SpecialMove::EnPassant(target_pawn) => {
+        self.push_move(mov.target, Some(piece));
+        self.push_move(target_pawn, None);
+      }
+
+      SpecialMove::Promotion => {
+        self.push_move(mov.target, Some(Piece { color: piece.color, piece: PieceType::Queen, moved: true }));
+      }
+
+      SpecialMove::None => {
+        self.push_move(mov.target, Some(piece));
+      }
+    }
+
+    self.last_move = Some(mov);
+    self.old_stack.clear();
+    self.commit_moves();
+
+    self.color_to_move.reverse();
+    self.ply += 1;
+  }
+
+  /* K Q k q, only unmoved kings and rooks on their starting squares can castle */
+  pub fn castling_rights(&self) -> [bool; 4] {
+    let mut rights = [false; 4];
+
+    for (i, (_, rook)) in CASTLING_ROOKS.iter().enumerate() {
+      let (color, king) = if i < 2 { (Color::White, 39) } else { (Color::Black, 32) };
+
+      let unmoved = |idx: usize, piece_type: PieceType| {
+        match self.board[idx].piece {
+          Some(piece) => piece.piece == piece_type && piece.color == color && !piece.moved,
+          None => false
+        }
+      };
+
+      rights[i] = unmoved(king, PieceType::King) && unmoved(*rook, PieceType::Rook);
+    }
+
+    rights
+  }
+
+  pub fn select(&mut self, mouse: &mut MouseHandler, layout: &Layout) {
+    if let Some(position) = mouse.clicked {
+      self.click(layout.square_at(position), position);
+      mouse.reset_drag();
+      return;
+    }
+
     if mouse.started_drag == false { return; }
 
+    /* dragging something other than the piece picked with a click picks that up instead */
+    if let Some(piece) = self.current_select {
+      if self.get_board_index_from_pos(mouse.start.unwrap(), layout).map(|(idx, _, _)| idx) != Some(piece.origin) {
+        self.deselect();
+      }
+    }
+
     match self.current_select {
       Some(_) => {
         if !mouse.drag_completed { return; }
 
-        match (self.get_board_index_from_pos(mouse.end.unwrap()), self.current_select) {
+        match (self.get_board_index_from_pos(mouse.end.unwrap(), layout), self.current_select) {
           (Some((idx, _, _)), Some(mut piece)) => {
             piece.dest = Some(idx);
             self.try_move(piece);
@@ -200,77 +282,84 @@ impl Board {
       }
 
       _ => {
-        let ret = self.get_board_index_from_pos(mouse.start.unwrap());
+        let ret = self.get_board_index_from_pos(mouse.start.unwrap(), layout);
 
         if ret.is_none() { return; }
 
-        let (pos, rank, file) = ret.unwrap();
+        self.pick(ret.unwrap().0, mouse.current.unwrap());
+      }
+    }
+  }
 
-        let tile = self.board[pos];
+  /* click a piece to pick it up, then click where it should go */
+  pub fn click(&mut self, idx: Option<usize>, position: [f64; 2]) {
+    log::trace!(target: "input", "click on {}", idx.map_or(String::from("nothing"), square_name));
 
-        if tile.piece.is_none() { return };
+    let piece = idx.and_then(|idx| self.board[idx].piece);
 
-        generate_piece_moves(self, pos, file, tile.piece.unwrap());
+    match (self.current_select, idx) {
+      /* clicking the same piece again puts it back */
+      (Some(select), Some(idx)) if idx == select.origin => self.deselect(),
 
-        self.current_select = Some(SelectedPiece {
-          piece: tile.piece.unwrap(),
-          origin_file: file,
-          origin_rank: rank,
-          origin: pos,
-          pos: mouse.current.unwrap(),
-          dest: None
-        });
+      (Some(select), Some(idx)) if piece.is_some_and(|piece| piece.color == select.piece.color) => {
+        self.deselect();
+        self.pick(idx, position);
+      }
 
-        self.board[pos].piece = None;
+      (Some(mut select), Some(idx)) => {
+        select.dest = Some(idx);
+        self.try_move(select);
+        self.current_select = None;
       }
-    }
-  }
 
-  pub fn unhighlight_tiles(&mut self) {
-    let mut pos = self.selected.pop();
+      (Some(_), None) => self.deselect(),
+
+      (None, Some(idx)) => {
+        if piece.is_some_and(|piece| piece.color == self.color_to_move) {
+          self.pick(idx, position);
+        }
+      }
 
-    while let Some(idx) = pos {
-      self.board[idx].selected = false;
-      pos = self.selected.pop();
+      (None, None) => {}
     }
   }
 
-  pub fn highlight_tile(&mut self, idx: usize) {
-    self.selected.push(idx);
-    self.board[idx].selected = true;
-  }
+  /* lifts the piece off the board and generates its moves */
+  fn pick(&mut self, pos: usize, position: [f64; 2]) {
+    let tile = self.board[pos];
 
-  pub fn get_piece_at(&self, idx: usize) -> Option<Piece> {
-    self.board[idx].piece
+    if tile.piece.is_none() { return };
+
+    generate_piece_moves(self, pos, pos % 8, tile.piece.unwrap());
+
+    self.current_select = Some(SelectedPiece {
+      piece: tile.piece.unwrap(),
+      origin_file: pos % 8,
+      origin_rank: pos / 8,
+      origin: pos,
+      pos: position,
+      dest: None
+    });
+
+    self.board[pos].piece = None;
   }
 
-  pub fn get_tile_at(&self, idx: usize) -> Tile {
-    self.board[idx]
+  /* puts a lifted piece back where it came from */
+  pub fn deselect(&mut self) {
+    if let Some(piece) = self.current_select.take() {
+      self.board[piece.origin].piece = Some(piece.piece);
+      self.moves.clear();
+    }
   }
 
-  fn is_inside_rect(&self, point: [f64; 2], rect: [f64; 4]) -> bool {
-    let (x, y) = (point[0], point[1]);
-    let (left, top, width, height) = (rect[0], rect[1], rect[2], rect[3]);
-    x >= left && x <= left + width && y >= top && y <= top + height
+  pub fn get_piece_at(&self, idx: usize) -> Option<Piece> {
+    self.board[idx].piece
   }
 
-  pub fn get_board_index_from_pos(&self, position: [f64; 2]) -> Option<(usize, usize, usize)> {
-    for file in 0 .. 8 {
-      for rank in 0 .. 8 {
-        let rect = [
-          rank as f64 * PIECE_HEIGHT,
-          file as f64 * PIECE_WIDTH,
-          PIECE_WIDTH,
-          PIECE_HEIGHT
-        ];
-
-        if self.is_inside_rect(position, rect) {
-          return Some((rank * 8 + file, rank, file));
-        }
-      }
-    }
+  pub fn get_board_index_from_pos(&self, position: [f64; 2], layout: &Layout) -> Option<(usize, usize, usize)> {
+    let idx = layout.square_at(position)?;
 
-    return None;
+    return Some((idx, idx / 8, idx % 8));
   }
 
   pub fn from_fen(fen: &str) -> Self {
@@ -280,15 +369,17 @@ impl Board {
       moves: Vec::new(),
       color_to_move: Color::White,
       last_move: None,
-      selected: Vec::new(),
+      ply: 0,
       old_stack: Vec::new(),
       stack: Vec::new()
     };
 
+    let mut fields = fen.split_whitespace();
+
     let mut file = 0;
     let mut rank = 0;
 
-    for c in fen.chars() {
+    for c in fields.next().unwrap_or("").chars() {
       if c == '/' {
         rank = 0;
         file += 1;
@@ -308,6 +399,38 @@ impl Board {
       }
     }
 
+    if fields.next() == Some("b") {
+      board.color_to_move = Color::Black;
+    }
+
+    /* castling rights are kept as unmoved kings and rooks */
+    if let Some(castling) = fields.next() {
+      for (right, rook) in CASTLING_ROOKS {
+        if !castling.contains(right) {
+          if let Some(piece) = &mut board.board[rook].piece {
+            piece.moved = true;
+          }
+        }
+      }
+    }
+
+    /* the double push that made the en passant square possible becomes the last move */
+    if let Some(idx) = fields.next().and_then(parse_square) {
+      let (row, color) = if board.color_to_move == Color::White { (2, Color::Black) } else { (5, Color::White) };
+
+      /* only rank 6 with white to move or rank 3 with black to move, anything else is ignored */
+      if idx % 8 == row {
+        let (start, target) = if color == Color::Black { (idx - 1, idx + 1) } else { (idx + 1, idx - 1) };
+
+        if board.board[target].piece.is_some_and(|piece| piece.piece == PieceType::Pawn && piece.color == color) {
+          board.last_move = Some(new_move(start, target));
+        }
+      }
+    }
+
+    let fullmove = fields.nth(1).and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+    board.ply = (fullmove.max(1) - 1) * 2 + if board.color_to_move == Color::Black { 1 } else { 0 };
+
     return board;
   }
 
@@ -347,6 +470,68 @@ impl Board {
       if file < 7 { fen.push('/'); }
     }
 
+    fen.push_str(if self.color_to_move == Color::White { " w " } else { " b " });
+
+    fen.push_str(&self.castling_field());
+    fen.push(' ');
+
+    match self.en_passant_square() {
+      Some(idx) => fen.push_str(&square_name(idx)),
+      None => fen.push('-')
+    }
+
+    fen.push_str(&format!(" 0 {}", self.ply / 2 + 1));
+
     return fen;
   }
-}
\ No newline at end of file
+
+  /* KQkq as in a fen, - when nobody can castle */
+  fn castling_field(&self) -> String {
+    let rights = self.castling_rights();
+    let field = CASTLING_ROOKS.iter().enumerate().filter(|(i, _)| rights[*i]).map(|(_, (right, _))| *right).collect::<String>();
+
+    if field.is_empty() { String::from("-") } else { field }
+  }
+
+  /* false when color can't mate by any series of moves, e.g. a lone king or king and knight against a king */
+  pub fn has_mating_material(&self, color: Color) -> bool {
+    let mut own = Vec::new();
+    let mut opponent = 0;
+
+    for piece in self.board.iter().filter_map(|tile| tile.piece) {
+      if piece.piece == PieceType::King { continue; }
+
+      if piece.color == color { own.push(piece.piece); } else { opponent += 1; }
+    }
+
+    match own.as_slice() {
+      [] => false,
+      [PieceType::Knight] | [PieceType::Bishop] => opponent > 0,
+      _ => true
+    }
+  }
+
+  /* the square a pawn skipped over with its last two square push */
+  pub fn en_passant_square(&self) -> Option<usize> {
+    let last_move = self.last_move?;
+    let piece = self.board[last_move.target].piece?;
+
+    if piece.piece == PieceType::Pawn && last_move.start.abs_diff(last_move.target) == 2 {
+      return Some((last_move.start + last_move.target) / 2);
+    }
+
+    None
+  }
+}
+
+/* e.g. 36 -> e4 */
+pub fn square_name(idx: usize) -> String {
+  format!("{}{}", (b'a' + (idx / 8) as u8) as char, 8 - idx % 8)
+}
+/* e.g. e4 -> 36 */
+pub fn parse_square(name: &str) -> Option<usize> {
+  match name.as_bytes() {
+    [file @ b'a' ..= b'h', rank @ b'1' ..= b'8'] => Some((file - b'a') as usize * 8 + (b'8' - rank) as usize),
+    _ => None
+  }
+}
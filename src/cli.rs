@@ -0,0 +1,436 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chess::annotation::MarkColor;
+use chess::board::parse_square;
+use chess::book::BookSelection;
+use chess::clock::TimeControl;
+use chess::layout::DEFAULT_SQUARE;
+use chess::logging;
+use chess::piece::Color;
+use chess::render::{ Diagram, GifOptions };
+
+pub const USAGE: &str = "usage: chess [options]
+
+  --fen <fen>              start from this position
+  --pgn <file>             open a game, its last position is shown
+  --play-as <white|black>  the engine plays the other side
+  --engine-depth <plies>   how deep the engine searches
+  --threads <n>            search threads, 1 always gives the same move (all cores)
+  --book-selection <how>   weighted picks book moves at random by weight, best always
+                           plays the most popular one (weighted)
+  --time-control <tc>      e.g. 5+3, 15d5 or 40/90+30,30+30
+  --tablebases <dir>       syzygy .rtbw and .rtbz files (assets/tablebases)
+  --theme <file>           board colors, see assets/themes
+  --pieces <file>          piece set descriptor, see assets/pieces
+  --save-pgn <file>        write the game here on exit
+  --gif-delay <s>          seconds every position is shown in gifs saved with ctrl+g (1)
+  --gif-final-pause <s>    extra seconds on their last position (3)
+  --tui                    play in the terminal instead of a window, --pieces is ignored
+                           and logs only go to --log-file
+  --log <levels>           e.g. info or warn,engine=debug (warn)
+  --log-file <file>        append logs here instead of stderr
+  -h, --help               show this
+
+log targets are rules, input, rendering and engine
+
+in the window the arrows step through the moves played, enter shows a square cursor that
+the arrows move instead until escape hides it again, and tab opens a box for typing moves
+
+  chess render --help      drawing a position to a png
+  chess gif --help         playing a game back in a gif";
+
+pub const RENDER_USAGE: &str = "usage: chess render [options]
+
+draws a position to a png without opening a window
+
+  --fen <fen>              the position, the starting one if left out
+  --out <file>             where the png goes, - for stdout (board.png)
+  --last-move <e2e4>       highlight the squares of a move
+  --arrow <Ge2e4>          an arrow, can be repeated
+  --mark <Re4>             a colored square, can be repeated
+  --coordinates            file and rank labels
+  --flip                   black at the bottom
+  --size <px>              size of a square (64)
+  --theme <file>           board colors, see assets/themes
+  --pieces <file>          piece set descriptor, see assets/pieces
+  --log <levels>           e.g. info or warn,rendering=debug (warn)
+  --log-file <file>        append logs here instead of stderr
+  -h, --help               show this
+
+arrows and marks start with a color, G, R, Y or B, green when left out";
+
+pub const GIF_USAGE: &str = "usage: chess gif --pgn <file> [options]
+
+plays a game back in an animated gif without opening a window
+
+  --pgn <file>             the game, its arrows and marks are drawn too
+  --out <file>             where the gif goes, - for stdout (game.gif)
+  --delay <s>              seconds every position is shown (1)
+  --final-pause <s>        extra seconds on the last position (3)
+  --no-highlights          don't highlight the last move
+  --coordinates            file and rank labels
+  --flip                   black at the bottom
+  --size <px>              size of a square (64)
+  --theme <file>           board colors, see assets/themes
+  --pieces <file>          piece set descriptor, see assets/pieces
+  --log <levels>           e.g. info or warn,rendering=debug (warn)
+  --log-file <file>        append logs here instead of stderr
+  -h, --help               show this";
+
+/* every option that takes a value */
+const OPTIONS: [&str; 15] = [
+  "--fen", "--pgn", "--play-as", "--engine-depth", "--threads", "--book-selection", "--time-control", "--tablebases", "--theme", "--pieces", "--save-pgn",
+  "--gif-delay", "--gif-final-pause", "--log", "--log-file"
+];
+
+const RENDER_OPTIONS: [&str; 10] = ["--fen", "--out", "--last-move", "--arrow", "--mark", "--size", "--theme", "--pieces", "--log", "--log-file"];
+
+const GIF_OPTIONS: [&str; 9] = ["--pgn", "--out", "--delay", "--final-pause", "--size", "--theme", "--pieces", "--log", "--log-file"];
+
+#[derive(Debug)]
+pub struct Options {
+  pub fen: Option<String>,
+  pub pgn: Option<PathBuf>,
+  pub play_as: Option<Color>,
+  pub engine_depth: Option<u8>,
+  pub threads: Option<usize>,
+  pub book_selection: Option<BookSelection>,
+  pub time_control: Option<TimeControl>,
+  pub tablebases: Option<PathBuf>,
+  pub theme: Option<PathBuf>,
+  pub pieces: Option<PathBuf>,
+  pub save_pgn: Option<PathBuf>,
+  /* only the delay and final pause, the rest follows the board when saving */
+  pub gif: GifOptions,
+  pub tui: bool,
+  pub log: String,
+  pub log_file: Option<PathBuf>,
+  pub help: bool
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Options {
+      fen: None,
+      pgn: None,
+      play_as: None,
+      engine_depth: None,
+      threads: None,
+      book_selection: None,
+      time_control: None,
+      tablebases: None,
+      theme: None,
+      pieces: None,
+      save_pgn: None,
+      gif: GifOptions::default(),
+      tui: false,
+      log: String::from(logging::DEFAULT_LEVELS),
+      log_file: None,
+      help: false
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct RenderOptions {
+  pub fen: Option<String>,
+  pub out: PathBuf,
+  pub diagram: Diagram,
+  pub size: u32,
+  pub theme: Option<PathBuf>,
+  pub pieces: Option<PathBuf>,
+  pub log: String,
+  pub log_file: Option<PathBuf>,
+  pub help: bool
+}
+
+impl Default for RenderOptions {
+  fn default() -> Self {
+    RenderOptions {
+      fen: None,
+      out: PathBuf::from("board.png"),
+      diagram: Diagram::default(),
+      size: DEFAULT_SQUARE as u32,
+      theme: None,
+      pieces: None,
+      log: String::from(logging::DEFAULT_LEVELS),
+      log_file: None,
+      help: false
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct GifCommand {
+  pub pgn: PathBuf,
+  pub out: PathBuf,
+  pub gif: GifOptions,
+  pub size: u32,
+  pub theme: Option<PathBuf>,
+  pub pieces: Option<PathBuf>,
+  pub log: String,
+  pub log_file: Option<PathBuf>,
+  pub help: bool
+}
+
+/* --name=value split up, anything not starting with -- is an error */
+fn split_arg(arg: &str) -> Result<(String, Option<String>), String> {
+  let (name, inline) = match arg.split_once('=') {
+    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+    None => (arg.to_string(), None)
+  };
+
+  if !name.starts_with("--") { return Err(format!("unexpected argument '{}'", arg)); }
+
+  Ok((name, inline))
+}
+
+fn parse_size(value: &str) -> Result<u32, String> {
+  let size = value.parse::<u32>().ok().filter(|size| (8 ..= 1024).contains(size));
+
+  size.ok_or_else(|| format!("--size takes a square size from 8 to 1024 pixels, not '{}'", value))
+}
+
+fn parse_seconds(name: &str, value: &str) -> Result<Duration, String> {
+  let seconds = value.parse::<f64>().ok().filter(|s| (0.0 ..= 600.0).contains(s)).map(Duration::from_secs_f64);
+
+  seconds.ok_or_else(|| format!("{} takes a number of seconds, not '{}'", name, value))
+}
+
+/* a square or a move with an optional color letter in front, as in [%csl Re4] and [%cal Ge2e4] */
+fn parse_mark(text: &str, squares: usize) -> Option<(MarkColor, Vec<usize>)> {
+  let (color, rest) = match text.chars().next().and_then(MarkColor::from_letter) {
+    Some(color) => (color, &text[1 ..]),
+    None => (MarkColor::Green, text)
+  };
+
+  if rest.len() != squares * 2 { return None; }
+
+  let squares = (0 .. squares).map(|i| rest.get(i * 2 .. i * 2 + 2).and_then(parse_square)).collect::<Option<Vec<_>>>()?;
+
+  Some((color, squares))
+}
+
+/* --name value or --name=value, args without the program name */
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+  let mut options = Options::default();
+  let mut args = args.into_iter();
+
+  while let Some(arg) = args.next() {
+    if arg == "-h" || arg == "--help" {
+      options.help = true;
+      continue;
+    }
+
+    if arg == "--tui" {
+      options.tui = true;
+      continue;
+    }
+
+    let (name, inline) = split_arg(&arg)?;
+    if !OPTIONS.contains(&name.as_str()) { return Err(format!("unknown option {}", name)); }
+
+    let value = match inline.or_else(|| args.next()) {
+      Some(value) => value,
+      None => return Err(format!("{} needs a value", name))
+    };
+
+    match name.as_str() {
+      "--fen" => options.fen = Some(value),
+      "--pgn" => options.pgn = Some(PathBuf::from(value)),
+
+      "--play-as" => {
+        options.play_as = Some(match value.as_str() {
+          "white" | "w" => Color::White,
+          "black" | "b" => Color::Black,
+          _ => return Err(format!("--play-as takes white or black, not '{}'", value))
+        });
+      }
+
+      "--engine-depth" => {
+        let depth = value.parse::<u8>().ok().filter(|depth| *depth > 0);
+        options.engine_depth = Some(depth.ok_or_else(|| format!("--engine-depth takes a number of plies, not '{}'", value))?);
+      }
+
+      "--threads" => {
+        let threads = value.parse::<usize>().ok().filter(|threads| (1 ..= 256).contains(threads));
+        options.threads = Some(threads.ok_or_else(|| format!("--threads takes a number from 1 to 256, not '{}'", value))?);
+      }
+
+      "--book-selection" => {
+        options.book_selection = Some(match value.as_str() {
+          "weighted" => BookSelection::WeightedRandom,
+          "best" => BookSelection::Best,
+          _ => return Err(format!("--book-selection takes weighted or best, not '{}'", value))
+        });
+      }
+
+      "--time-control" => {
+        options.time_control = Some(TimeControl::parse(&value).ok_or_else(|| format!("'{}' isn't a time control", value))?);
+      }
+
+      "--tablebases" => options.tablebases = Some(PathBuf::from(value)),
+      "--theme" => options.theme = Some(PathBuf::from(value)),
+      "--pieces" => options.pieces = Some(PathBuf::from(value)),
+      "--save-pgn" => options.save_pgn = Some(PathBuf::from(value)),
+      "--gif-delay" => options.gif.delay = parse_seconds(&name, &value)?,
+      "--gif-final-pause" => options.gif.final_pause = parse_seconds(&name, &value)?,
+
+      "--log" => {
+        logging::parse_levels(&value)?;
+        options.log = value;
+      }
+
+      "--log-file" => options.log_file = Some(PathBuf::from(value)),
+
+      _ => unreachable!()
+    }
+  }
+
+  if options.fen.is_some() && options.pgn.is_some() {
+    return Err(String::from("--fen and --pgn can't be used together"));
+  }
+
+  Ok(options)
+}
+
+/* the args after render */
+pub fn parse_render(args: impl IntoIterator<Item = String>) -> Result<RenderOptions, String> {
+  let mut options = RenderOptions::default();
+  let mut args = args.into_iter();
+
+  while let Some(arg) = args.next() {
+    if arg == "-h" || arg == "--help" {
+      options.help = true;
+      continue;
+    }
+
+    let (name, inline) = split_arg(&arg)?;
+
+    /* the flags */
+    match name.as_str() {
+      "--coordinates" | "--flip" if inline.is_some() => return Err(format!("{} doesn't take a value", name)),
+      "--coordinates" => { options.diagram.coordinates = true; continue; }
+      "--flip" => { options.diagram.flipped = true; continue; }
+      _ => {}
+    }
+
+    if !RENDER_OPTIONS.contains(&name.as_str()) { return Err(format!("unknown option {}", name)); }
+
+    let value = match inline.or_else(|| args.next()) {
+      Some(value) => value,
+      None => return Err(format!("{} needs a value", name))
+    };
+
+    match name.as_str() {
+      "--fen" => options.fen = Some(value),
+      "--out" => options.out = PathBuf::from(value),
+
+      "--last-move" => {
+        let squares = (value.get(.. 2).and_then(parse_square), value.get(2 ..).and_then(parse_square));
+
+        match squares {
+          (Some(from), Some(to)) => options.diagram.last_move = Some((from, to)),
+          _ => return Err(format!("--last-move takes two squares like e2e4, not '{}'", value))
+        }
+      }
+
+      "--arrow" => match parse_mark(&value, 2) {
+        Some((color, squares)) => options.diagram.annotations.toggle_arrow(color, squares[0], squares[1]),
+        None => return Err(format!("'{}' isn't an arrow, e.g. Ge2e4", value))
+      }
+
+      "--mark" => match parse_mark(&value, 1) {
+        Some((color, squares)) => options.diagram.annotations.toggle_square(color, squares[0]),
+        None => return Err(format!("'{}' isn't a square, e.g. Re4", value))
+      }
+
+      "--size" => options.size = parse_size(&value)?,
+
+      "--theme" => options.theme = Some(PathBuf::from(value)),
+      "--pieces" => options.pieces = Some(PathBuf::from(value)),
+
+      "--log" => {
+        logging::parse_levels(&value)?;
+        options.log = value;
+      }
+
+      "--log-file" => options.log_file = Some(PathBuf::from(value)),
+
+      _ => unreachable!()
+    }
+  }
+
+  Ok(options)
+}
+
+/* the args after gif */
+pub fn parse_gif(args: impl IntoIterator<Item = String>) -> Result<GifCommand, String> {
+  let mut pgn = None;
+  let mut command = GifCommand {
+    pgn: PathBuf::new(),
+    out: PathBuf::from("game.gif"),
+    gif: GifOptions::default(),
+    size: DEFAULT_SQUARE as u32,
+    theme: None,
+    pieces: None,
+    log: String::from(logging::DEFAULT_LEVELS),
+    log_file: None,
+    help: false
+  };
+
+  let mut args = args.into_iter();
+
+  while let Some(arg) = args.next() {
+    if arg == "-h" || arg == "--help" {
+      command.help = true;
+      continue;
+    }
+
+    let (name, inline) = split_arg(&arg)?;
+
+    /* the flags */
+    match name.as_str() {
+      "--no-highlights" | "--coordinates" | "--flip" if inline.is_some() => return Err(format!("{} doesn't take a value", name)),
+      "--no-highlights" => { command.gif.highlights = false; continue; }
+      "--coordinates" => { command.gif.coordinates = true; continue; }
+      "--flip" => { command.gif.flipped = true; continue; }
+      _ => {}
+    }
+
+    if !GIF_OPTIONS.contains(&name.as_str()) { return Err(format!("unknown option {}", name)); }
+
+    let value = match inline.or_else(|| args.next()) {
+      Some(value) => value,
+      None => return Err(format!("{} needs a value", name))
+    };
+
+    match name.as_str() {
+      "--pgn" => pgn = Some(PathBuf::from(&value)),
+      "--out" => command.out = PathBuf::from(&value),
+      "--delay" => command.gif.delay = parse_seconds(&name, &value)?,
+      "--final-pause" => command.gif.final_pause = parse_seconds(&name, &value)?,
+      "--size" => command.size = parse_size(&value)?,
+      "--theme" => command.theme = Some(PathBuf::from(&value)),
+      "--pieces" => command.pieces = Some(PathBuf::from(&value)),
+
+      "--log" => {
+        logging::parse_levels(&value)?;
+        command.log = value;
+      }
+
+      "--log-file" => command.log_file = Some(PathBuf::from(&value)),
+
+      _ => unreachable!()
+    }
+  }
+
+  match pgn {
+    Some(pgn) => command.pgn = pgn,
+    None if command.help => {}
+    None => return Err(String::from("--pgn is needed"))
+  }
+
+  Ok(command)
+}
@@ -0,0 +1,327 @@
+use std::time::Duration;
+
+use crate::board::{ Board, GameResult };
+use crate::piece::Color;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Delay {
+  None,
+  /* time used up to the delay is given back after the move */
+  Bronstein(Duration),
+  /* the clock only starts running once the delay is used up */
+  Simple(Duration)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Period {
+  /* moves to make in this period, None for the rest of the game */
+  pub moves: Option<u32>,
+  pub time: Duration,
+  pub increment: Duration,
+  pub delay: Delay
+}
+
+#[derive(Clone, Debug)]
+pub struct TimeControl {
+  pub periods: Vec<Period>
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+  pub control: TimeControl,
+  pub turn: Color,
+  pub flagged: Option<Color>,
+  remaining: [Duration; 2],
+  period: [usize; 2],
+  moves: [u32; 2],
+  used: Duration
+}
+
+fn side(color: Color) -> usize {
+  if color == Color::White { 0 } else { 1 }
+}
+
+/* None for anything that isn't a finite, non negative number of seconds that fits a Duration */
+fn seconds(s: &str) -> Option<Duration> {
+  s.parse::<f64>().ok().and_then(|s| Duration::try_from_secs_f64(s).ok())
+}
+
+impl Period {
+  /* [moves/]minutes[+increment][d delay | b delay], increment and delay in seconds */
+  fn parse(s: &str) -> Option<Self> {
+    let (moves, rest) = match s.split_once('/') {
+      Some((moves, rest)) => (Some(moves.parse::<u32>().ok().filter(|moves| *moves > 0)?), rest),
+      None => (None, s)
+    };
+
+    let (rest, delay) = if let Some((rest, delay)) = rest.split_once('d') {
+      (rest, Delay::Simple(seconds(delay)?))
+    } else if let Some((rest, delay)) = rest.split_once('b') {
+      (rest, Delay::Bronstein(seconds(delay)?))
+    } else {
+      (rest, Delay::None)
+    };
+
+    let (time, increment) = match rest.split_once('+') {
+      Some((time, increment)) => (time, seconds(increment)?),
+      None => (rest, Duration::ZERO)
+    };
+
+    Some(Period { moves, time: seconds(time)?.checked_mul(60)?, increment, delay })
+  }
+}
+
+impl TimeControl {
+  /* periods separated by commas, e.g. 5+3, 15d5 or 40/90+30,30+30 */
+  pub fn parse(s: &str) -> Option<Self> {
+    let periods = s.split(',').map(|period| Period::parse(period.trim())).collect::<Option<Vec<_>>>()?;
+
+    if periods.is_empty() { return None; }
+
+    Some(TimeControl { periods })
+  }
+}
+
+impl Clock {
+  pub fn new(control: TimeControl, turn: Color) -> Self {
+    let time = control.periods[0].time;
+
+    Clock {
+      control,
+      turn,
+      flagged: None,
+      remaining: [time; 2],
+      period: [0; 2],
+      moves: [0; 2],
+      used: Duration::ZERO
+    }
+  }
+
+  pub fn remaining(&self, color: Color) -> Duration {
+    self.remaining[side(color)]
+  }
+
+  fn current_period(&self, color: Color) -> Period {
+    self.control.periods[self.period[side(color)]]
+  }
+
+  /* time used on a move that actually comes off the clock */
+  fn charged(&self, used: Duration) -> Duration {
+    match self.current_period(self.turn).delay {
+      Delay::Simple(delay) => used.saturating_sub(delay),
+      _ => used
+    }
+  }
+
+  pub fn tick(&mut self, dt: Duration) {
+    if self.flagged.is_some() { return; }
+
+    let charged = self.charged(self.used + dt) - self.charged(self.used);
+    let remaining = &mut self.remaining[side(self.turn)];
+
+    self.used += dt;
+    *remaining = remaining.saturating_sub(charged);
+
+    if remaining.is_zero() {
+      self.flagged = Some(self.turn);
+    }
+  }
+
+  /* called after every move, hands the clock over to the other side */
+  pub fn press(&mut self) {
+    if self.flagged.is_some() { return; }
+
+    let period = self.current_period(self.turn);
+    let i = side(self.turn);
+
+    if let Delay::Bronstein(delay) = period.delay {
+      self.remaining[i] = self.remaining[i].saturating_add(self.used.min(delay));
+    }
+
+    self.remaining[i] = self.remaining[i].saturating_add(period.increment);
+    self.moves[i] += 1;
+
+    /* the last period repeats, so 40/90 alone gives 90 minutes every 40 moves */
+    if period.moves.is_some_and(|moves| self.moves[i] >= moves) {
+      self.moves[i] = 0;
+      self.period[i] = (self.period[i] + 1).min(self.control.periods.len() - 1);
+      self.remaining[i] = self.remaining[i].saturating_add(self.control.periods[self.period[i]].time);
+    }
+
+    self.used = Duration::ZERO;
+    self.turn.reverse();
+  }
+
+  /* once a side has flagged it loses, or draws when the other side has nothing left to mate with */
+  pub fn timeout_result(&self, board: &Board) -> Option<GameResult> {
+    let mut winner = self.flagged?;
+    winner.reverse();
+
+    Some(if board.has_mating_material(winner) { GameResult::Win(winner) } else { GameResult::Draw })
+  }
+
+  /* for taking moves back, the clock switches sides without any increment */
+  pub fn set_turn(&mut self, color: Color) {
+    if self.turn != color {
+      self.turn = color;
+      self.used = Duration::ZERO;
+    }
+  }
+}
+
+/* h:mm:ss, m:ss or s.t under ten seconds */
+pub fn format_time(time: Duration) -> String {
+  let secs = time.as_secs();
+
+  if secs >= 3600 {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+  } else if secs >= 10 {
+    format!("{}:{:02}", secs / 60, secs % 60)
+  } else {
+    format!("0:{:02}.{}", secs, time.subsec_millis() / 100)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_time_controls() {
+    let blitz = TimeControl::parse("5+3").unwrap();
+    assert_eq!(blitz.periods.len(), 1);
+    assert_eq!(blitz.periods[0].time, Duration::from_secs(300));
+    assert_eq!(blitz.periods[0].increment, Duration::from_secs(3));
+    assert_eq!(blitz.periods[0].delay, Delay::None);
+
+    let delayed = TimeControl::parse("15d5").unwrap();
+    assert_eq!(delayed.periods[0].time, Duration::from_secs(900));
+    assert_eq!(delayed.periods[0].delay, Delay::Simple(Duration::from_secs(5)));
+
+    let bronstein = TimeControl::parse("0.5b2").unwrap();
+    assert_eq!(bronstein.periods[0].time, Duration::from_secs(30));
+    assert_eq!(bronstein.periods[0].delay, Delay::Bronstein(Duration::from_secs(2)));
+
+    let classical = TimeControl::parse("40/90+30, 30+30").unwrap();
+    assert_eq!(classical.periods.len(), 2);
+    assert_eq!(classical.periods[0].moves, Some(40));
+    assert_eq!(classical.periods[0].time, Duration::from_secs(90 * 60));
+    assert_eq!(classical.periods[1].moves, None);
+    assert_eq!(classical.periods[1].increment, Duration::from_secs(30));
+  }
+
+  fn clock(control: &str) -> Clock {
+    Clock::new(TimeControl::parse(control).unwrap(), Color::White)
+  }
+
+  fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+  }
+
+  #[test]
+  fn ticks_the_side_to_move() {
+    let mut clock = clock("1");
+
+    clock.tick(secs(10));
+    assert_eq!(clock.remaining(Color::White), secs(50));
+    assert_eq!(clock.remaining(Color::Black), secs(60));
+
+    clock.press();
+    assert_eq!(clock.turn, Color::Black);
+
+    clock.tick(secs(15));
+    assert_eq!(clock.remaining(Color::White), secs(50));
+    assert_eq!(clock.remaining(Color::Black), secs(45));
+  }
+
+  #[test]
+  fn adds_the_increment_after_a_move() {
+    let mut clock = clock("1+2");
+
+    clock.tick(secs(10));
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(52));
+
+    /* even for a move that took no time */
+    clock.press();
+    assert_eq!(clock.remaining(Color::Black), secs(62));
+  }
+
+  #[test]
+  fn gives_back_bronstein_delay_up_to_the_time_used() {
+    let mut clock = clock("1b5");
+
+    clock.tick(secs(3));
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(60));
+
+    clock.tick(secs(8));
+    clock.press();
+    assert_eq!(clock.remaining(Color::Black), secs(57));
+  }
+
+  #[test]
+  fn uses_simple_delay_before_the_main_time() {
+    let mut clock = clock("1d5");
+
+    clock.tick(secs(3));
+    assert_eq!(clock.remaining(Color::White), secs(60));
+
+    clock.tick(secs(4));
+    assert_eq!(clock.remaining(Color::White), secs(58));
+
+    /* the delay starts over on every move */
+    clock.press();
+    clock.press();
+    clock.tick(secs(5));
+    assert_eq!(clock.remaining(Color::White), secs(58));
+  }
+
+  #[test]
+  fn moves_to_the_next_period_after_its_moves() {
+    let mut clock = clock("2/1, 2+10");
+
+    clock.tick(secs(5));
+    clock.press();
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(55));
+
+    /* the second move finishes the period and the next one's time is added */
+    clock.tick(secs(5));
+    clock.press();
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(50 + 120));
+
+    /* with the next period's increment */
+    clock.tick(secs(5));
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(165 + 10));
+  }
+
+  #[test]
+  fn flags_at_zero() {
+    let mut clock = clock("0.5");
+
+    clock.tick(secs(29));
+    assert_eq!(clock.flagged, None);
+
+    clock.tick(secs(2));
+    assert_eq!(clock.remaining(Color::White), Duration::ZERO);
+    assert_eq!(clock.flagged, Some(Color::White));
+    assert_eq!(clock.timeout_result(&Board::new()), Some(GameResult::Win(Color::Black)));
+    assert_eq!(clock.timeout_result(&Board::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1")), Some(GameResult::Draw));
+
+    /* the clock stops once a side has flagged */
+    clock.press();
+    clock.tick(secs(1));
+    assert_eq!(clock.turn, Color::White);
+    assert_eq!(clock.remaining(Color::Black), secs(30));
+  }
+
+  #[test]
+  fn rejects_bad_time_controls() {
+    for text in ["", "five", "-5", "5+-3", "0/5", "5+", "5+inf", "inf", "nan", "5+nan", "1e300", "5d1e300", "1e18"] {
+      assert!(TimeControl::parse(text).is_none(), "{} was accepted", text);
+    }
+  }
+}
@@ -1,5 +1,8 @@
 use piston_window::*;
 
+/* how far the mouse has to move while pressed before it counts as a drag */
+const DRAG_THRESHOLD: f64 = 4.0;
+
 /* MouseHandler::reset_drag should be called after drag values are used !! */
 #[derive(Debug)]
 pub struct MouseHandler {
@@ -8,9 +11,21 @@ pub struct MouseHandler {
   pub current: Option<[f64; 2]>,
   pub start: Option<[f64; 2]>,
   pub end: Option<[f64; 2]>,
+  /* set on release when the mouse didn't move far enough to drag */
+  pub clicked: Option<[f64; 2]>,
+  /* where the right button went down and came back up, set on release */
+  pub right_drag: Option<([f64; 2], [f64; 2])>,
+  right_start: Option<[f64; 2]>,
+  position: Option<[f64; 2]>,
   pressed: bool
 }
 
+impl Default for MouseHandler {
+  fn default() -> Self {
+    MouseHandler::new()
+  }
+}
+
 impl MouseHandler {
   pub fn new() -> Self {
     return MouseHandler {
@@ -19,7 +34,11 @@ impl MouseHandler {
       pressed: false,
       current: None,
       start: None,
-      end: None
+      end: None,
+      clicked: None,
+      right_drag: None,
+      right_start: None,
+      position: None
     }
   }
 
@@ -30,37 +49,56 @@ impl MouseHandler {
     self.current = None;
     self.start = None;
     self.end = None;
+    self.clicked = None;
   }
 
   pub fn handle_input(&mut self, input: &Input) {
     match input {
+      Input::Button(button_args) if button_args.button == Button::Mouse(MouseButton::Right) => {
+        if button_args.state == ButtonState::Press {
+          self.right_start = self.position;
+        } else if let (Some(start), Some(end)) = (self.right_start.take(), self.position) {
+          self.right_drag = Some((start, end));
+        }
+      }
+
       Input::Button(button_args) => {
         if button_args.button != Button::Mouse(MouseButton::Left) { return }
 
-        if self.drag_completed {
+        if self.drag_completed || self.clicked.is_some() {
           /* they clicked too much? idk retry */
           self.reset_drag();
         }
 
         if button_args.state == ButtonState::Press {
           self.pressed = true;
+          self.start = self.position;
         } else if button_args.state == ButtonState::Release && self.started_drag {
           self.end = self.current;
           self.drag_completed = true;
+        } else if button_args.state == ButtonState::Release && self.pressed {
+          self.pressed = false;
+          self.clicked = self.start.take();
         }
       }
 
       Input::Move(motion) => {
         match motion {
           Motion::MouseCursor(mouse) => {
-            if self.pressed {
-              self.started_drag = true;
-              self.current = Some(*mouse);
-            }
+            self.position = Some(*mouse);
 
             if self.pressed && self.start.is_none() {
               self.start = Some(*mouse);
             }
+
+            if let (true, Some(start)) = (self.pressed, self.start) {
+              let distance = (mouse[0] - start[0]).hypot(mouse[1] - start[1]);
+
+              if self.started_drag || distance > DRAG_THRESHOLD {
+                self.started_drag = true;
+                self.current = Some(*mouse);
+              }
+            }
           }
 
           _ => {}
@@ -70,4 +108,4 @@ impl MouseHandler {
       _ => {}
     }
   }
-}
\ No newline at end of file
+}
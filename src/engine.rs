@@ -0,0 +1,98 @@
+use std::io;
+use std::sync::Arc;
+
+use crate::board::Board;
+use crate::book::{ BookOptions, OpeningBook };
+use crate::move_generator::Move;
+use crate::notation;
+use crate::piece::Color;
+use crate::search::{ Search, SearchOptions };
+use crate::tablebase::{ Tablebase, TablebaseOptions };
+use crate::transposition::TranspositionTable;
+
+/* the computer player of the window and the terminal, book moves first, otherwise a search in the background */
+pub struct Engine {
+  /* the side it plays on its own, None leaves both to the human */
+  pub color: Option<Color>,
+  pub options: SearchOptions,
+  pub book: Option<OpeningBook>,
+  pub tablebase: Option<Arc<Tablebase>>,
+  tt: Arc<TranspositionTable>,
+  search: Option<Search>
+}
+
+/* a missing file just means there is none, anything else is worth a warning */
+fn open_default<T>(what: &str, result: io::Result<T>) -> Option<T> {
+  match result {
+    Ok(opened) => Some(opened),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+    Err(err) => {
+      log::warn!(target: "engine", "couldn't load {}: {}", what, err);
+      None
+    }
+  }
+}
+
+impl Default for Engine {
+  fn default() -> Self {
+    Engine::new()
+  }
+}
+
+impl Engine {
+  /* with the default book and tablebases if they are there */
+  pub fn new() -> Self {
+    Engine {
+      color: None,
+      options: SearchOptions::default(),
+      book: open_default("opening book", OpeningBook::open(BookOptions::default())),
+      tablebase: open_default("tablebases", Tablebase::open(TablebaseOptions::default())).map(Arc::new),
+      tt: Arc::new(TranspositionTable::new(64)),
+      search: None
+    }
+  }
+
+  pub fn to_move(&self, board: &Board) -> bool {
+    self.color == Some(board.color_to_move)
+  }
+
+  pub fn is_thinking(&self) -> bool {
+    self.search.is_some()
+  }
+
+  /* a search of its own sharing the engine's table, e.g. for a hint */
+  pub fn search(&self, board: &Board) -> Search {
+    Search::start(board, self.options, self.tt.clone(), self.tablebase.clone())
+  }
+
+  /* a book move to play straight away, otherwise a search starts and finished_move gives its move */
+  pub fn think(&mut self, board: &Board) -> Option<Move> {
+    if self.search.is_some() { return None; }
+
+    match self.book.as_mut().and_then(|book| book.pick(board)) {
+      Some(mov) => {
+        log::debug!(target: "engine", "book move {}", notation::to_san(board, mov));
+        Some(mov)
+      }
+
+      None => {
+        log::debug!(target: "engine", "searching to depth {} with {} threads", self.options.depth, self.options.threads);
+        self.search = Some(self.search(board));
+        None
+      }
+    }
+  }
+
+  /* the searched move once the search is done, board is the position it was started on */
+  pub fn finished_move(&mut self, board: &Board) -> Option<Move> {
+    let mov = self.search.take_if(|search| search.is_finished())?.wait()?;
+    log::debug!(target: "engine", "search picked {}", notation::to_san(board, mov));
+
+    Some(mov)
+  }
+
+  /* aborts the search, with the best move it had found */
+  pub fn stop(&mut self) -> Option<Move> {
+    self.search.take().and_then(Search::stop)
+  }
+}
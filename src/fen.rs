@@ -0,0 +1,128 @@
+use crate::board::{ Board, parse_square };
+use crate::move_generator::is_in_check;
+use crate::piece::{ Color, Piece, PieceType };
+
+/* Board::from_fen takes whatever it's given, this refuses anything that isn't a legal position */
+pub fn parse(fen: &str) -> Result<Board, String> {
+  let fields = fen.split_whitespace().collect::<Vec<_>>();
+
+  if !(4 ..= 6).contains(&fields.len()) {
+    return Err(String::from("a fen needs placement, side to move, castling and en passant fields"));
+  }
+
+  let ranks = fields[0].split('/').collect::<Vec<_>>();
+  if ranks.len() != 8 { return Err(format!("placement has {} ranks instead of 8", ranks.len())); }
+
+  for (i, rank) in ranks.iter().enumerate() {
+    let mut squares = 0;
+
+    for c in rank.chars() {
+      match c.to_digit(10) {
+        Some(n @ 1 ..= 8) => squares += n,
+        _ if Piece::from_fen(c).is_some() => squares += 1,
+        _ => return Err(format!("unexpected '{}' on rank {}", c, 8 - i))
+      }
+    }
+
+    if squares != 8 { return Err(format!("rank {} has {} squares", 8 - i, squares)); }
+  }
+
+  if fields[1] != "w" && fields[1] != "b" {
+    return Err(format!("side to move is '{}' instead of w or b", fields[1]));
+  }
+
+  let castling = fields[2];
+  let order = "KQkq";
+
+  if castling != "-" && !order.chars().filter(|c| castling.contains(*c)).eq(castling.chars()) {
+    return Err(format!("castling rights '{}' should be - or a subset of KQkq in that order", castling));
+  }
+
+  let en_passant = match fields[3] {
+    "-" => None,
+    square => Some(parse_square(square).ok_or_else(|| format!("'{}' isn't a square", square))?)
+  };
+
+  /* the square a pawn skipped over is on rank 6 with white to move and rank 3 with black to move */
+  let en_passant_rank = if fields[1] == "w" { '6' } else { '3' };
+
+  if en_passant.is_some() && !fields[3].ends_with(en_passant_rank) {
+    return Err(format!("en passant square {} isn't on rank {}", fields[3], en_passant_rank));
+  }
+
+  for number in &fields[4 ..] {
+    if number.parse::<usize>().is_err() { return Err(format!("'{}' isn't a move counter", number)); }
+  }
+
+  let board = Board::from_fen(fen);
+  validate(&board)?;
+
+  let rights = board.castling_rights();
+
+  for (i, right) in order.chars().enumerate() {
+    if castling.contains(right) && !rights[i] {
+      return Err(format!("castling right {} needs the king and rook on their starting squares", right));
+    }
+  }
+
+  if let Some(idx) = en_passant {
+    let behind = if board.color_to_move == Color::White { idx - 1 } else { idx + 1 };
+
+    if board.en_passant_square() != Some(idx) || board.board[idx].piece.is_some() || board.board[behind].piece.is_some() {
+      return Err(format!("no pawn can have just skipped over {}", fields[3]));
+    }
+  }
+
+  Ok(board)
+}
+
+/* the checks that don't need the fen text, kings, pawns on the back ranks and the side that just moved left in check */
+pub fn validate(board: &Board) -> Result<(), String> {
+  for color in [Color::White, Color::Black] {
+    let name = if color == Color::White { "white" } else { "black" };
+    let pieces = board.board.iter().filter_map(|tile| tile.piece).filter(|piece| piece.color == color).collect::<Vec<_>>();
+    let count = |piece_type: PieceType| pieces.iter().filter(|piece| piece.piece == piece_type).count();
+
+    if count(PieceType::King) != 1 { return Err(format!("{} needs exactly one king", name)); }
+    if count(PieceType::Pawn) > 8 { return Err(format!("{} has more than 8 pawns", name)); }
+    if pieces.len() > 16 { return Err(format!("{} has more than 16 pieces", name)); }
+  }
+
+  /* rows 0 and 7 are the 8th and 1st ranks */
+  if (0 .. 8 * 8).any(|idx| idx % 8 % 7 == 0 && board.board[idx].piece.is_some_and(|piece| piece.piece == PieceType::Pawn)) {
+    return Err(String::from("pawns can't stand on the first or last rank"));
+  }
+
+  let mut waiting = board.color_to_move;
+  waiting.reverse();
+
+  if is_in_check(board, waiting) {
+    return Err(String::from("the side that isn't to move is in check"));
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rejects_en_passant_squares_off_their_rank() {
+    for fen in ["4k3/8/8/8/8/8/8/4K3 w - h1 0 1", "4k3/8/8/8/8/8/8/4K3 w - a8 0 1", "4k3/8/8/8/8/8/8/4K3 b - e6 0 1", "4k3/8/8/8/8/8/8/4K3 w - e3 0 1"] {
+      assert!(parse(fen).is_err(), "{} was accepted", fen);
+
+      /* from_fen takes anything, it just ignores the square */
+      assert!(Board::from_fen(fen).last_move.is_none());
+    }
+  }
+
+  #[test]
+  fn reads_en_passant_squares() {
+    let white = parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+    assert_eq!(white.en_passant_square(), parse_square("d6"));
+
+    let black = parse("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1").unwrap();
+    assert_eq!(black.en_passant_square(), parse_square("d3"));
+  }
+}
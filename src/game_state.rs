@@ -1,27 +1,95 @@
 use crate::sprite::SpriteHandler;
-use crate::board::Board;
+use crate::board::{ self, Board, GameResult };
+use crate::clock::{ self, Clock, TimeControl };
+use crate::piece::Color;
 use crate::cursor::MouseHandler;
+use crate::move_generator::{ Move, SpecialMove };
+use crate::engine::Engine;
+use crate::zobrist;
+use crate::search::Search;
+use crate::notation;
+use crate::theme::{ self, PieceSet, Theme };
+use crate::animation::Animation;
+use crate::annotation::{ self, Annotations, MarkColor };
+use crate::pgn::{ self, Game };
+use crate::highlight::{ Highlights, Layer };
+use crate::piece::PieceType;
+use crate::move_generator::{ is_in_check, legal_moves };
+use crate::setup::{ self, Setup };
+use crate::assets::asset_path;
+use crate::render::{ self, GifOptions, Renderer };
 
-use crate::{ PIECE_HEIGHT, PIECE_WIDTH };
+use std::path::{ Path, PathBuf };
+use std::thread;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+use crate::layout::{ Layout, DEFAULT_SQUARE };
 
 use piston_window::*;
 
-macro_rules! rgb_to_color {
-    ($r:expr, $g:expr, $b:expr) => {
-      [$r as f32 / 255.0, $g as f32 / 255.0, $b as f32 / 255.0, 1.0]
-    };
+const DEFAULT_ANIMATION_DURATION: f64 = 0.2;
+/* saved to with Ctrl+S and opened with Ctrl+O */
+const PGN_PATH: &str = "game.pgn";
+
+/* cycled with M */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LegalMoveStyle {
+  Dots,
+  Squares,
+  Hidden
 }
 
-const DARK_SQUARE: [f32; 4] = rgb_to_color!(161, 111, 92);
-const LIGHT_SQUARE: [f32; 4] = rgb_to_color!(236, 211, 186);
-const SELECTED_SQUARE: [f32; 4] = rgb_to_color!(93, 142, 162);
-const LEGAL_DARK_SQUARE: [f32; 4] = rgb_to_color!(176, 39, 49);
-const LEGAL_LIGHT_SQUARE: [f32; 4] = rgb_to_color!(222, 62, 77);
+/* cycled with C, an empty string turns the clock off */
+const TIME_CONTROLS: [&str; 5] = ["", "5+3", "15+10", "10d5", "40/90+30,30+30"];
 
 pub struct GameState<'a> {
   pub sprite_handler: SpriteHandler<'a>,
   pub board: Board,
   pub mouse: MouseHandler,
+  pub engine: Engine,
+  /* a search for a hint, its move is only shown */
+  hint_search: Option<Search>,
+  pub show_book: bool,
+  pub show_coordinates: bool,
+  /* delay and final pause of gifs saved with Ctrl+G, orientation and labels follow the board */
+  pub gif_options: GifOptions,
+  pub legal_move_style: LegalMoveStyle,
+  /* seconds a move takes to slide into place, 0 turns animations off */
+  pub animation_duration: f64,
+  animation: Option<Animation>,
+  tablebase_result: Option<String>,
+  tablebase_key: u64,
+  pub clock: Option<Clock>,
+  pub result: Option<GameResult>,
+  time_control: usize,
+  clock_ply: usize,
+  history: Vec<Board>,
+  history_index: usize,
+  /* arrows and squares marked on each position in the history */
+  annotations: Vec<Annotations>,
+  pub highlights: Highlights,
+  /* moves queued while the engine is thinking, played in order once they're legal on the real board */
+  pub premoves: Vec<Move>,
+  /* the position with the premoves played and the human to move, where new premoves are picked */
+  premove_board: Option<Board>,
+  /* the position editor, open while Some */
+  pub setup: Option<Setup>,
+  pub layout: Layout,
+  pub theme: Theme,
+  themes: Vec<PathBuf>,
+  theme_index: usize,
+  piece_sets: Vec<PathBuf>,
+  piece_set_index: usize,
+  /* keyboard cursor over the squares, hidden until enter is pressed and again with escape */
+  pub cursor: Option<usize>,
+  /* the box for typing moves, open while Some */
+  move_input: Option<String>,
+  move_input_invalid: bool,
+  /* set when escape has nothing left to cancel */
+  pub quit: bool,
+  ctrl: bool,
+  shift: bool,
+  alt: bool,
   paused: bool
 }
 
@@ -31,10 +99,51 @@ impl <'a>GameState<'a> {
       sprite_handler,
       mouse: MouseHandler::new(),
       board: Board::new(),
+      engine: Engine::new(),
+      hint_search: None,
+      show_book: false,
+      show_coordinates: true,
+      gif_options: GifOptions::default(),
+      legal_move_style: LegalMoveStyle::Dots,
+      animation_duration: DEFAULT_ANIMATION_DURATION,
+      animation: None,
+      tablebase_result: None,
+      tablebase_key: 0,
+      clock: None,
+      result: None,
+      time_control: 0,
+      clock_ply: 0,
+      history: Vec::new(),
+      history_index: 0,
+      annotations: Vec::new(),
+      highlights: Highlights::default(),
+      premoves: Vec::new(),
+      premove_board: None,
+      setup: None,
+      layout: Layout::new(Layout::window_size(), false),
+      theme: Theme::default(),
+      /* themes and piece sets cycled with T and P */
+      themes: theme::list(&asset_path("themes")),
+      theme_index: 0,
+      piece_sets: theme::list(&asset_path("pieces")),
+      piece_set_index: 0,
+      cursor: None,
+      move_input: None,
+      move_input_invalid: false,
+      quit: false,
+      ctrl: false,
+      shift: false,
+      alt: false,
       paused: false
     };
 
-    state.sprite_handler.load();
+    state.history.push(state.board.clone());
+    state.annotations.push(Annotations::default());
+
+    /* the built in theme and pieces are the ones called default */
+    let is_default = |path: &PathBuf| path.file_stem().is_some_and(|stem| stem == "default");
+    state.theme_index = state.themes.iter().position(is_default).unwrap_or(0);
+    state.piece_set_index = state.piece_sets.iter().position(is_default).unwrap_or(0);
 
     return state;
   }
@@ -45,42 +154,780 @@ impl <'a>GameState<'a> {
         self.paused = !*focus;
       }
 
-      _ => self.mouse.handle_input(input)
+      Input::Resize(args) => self.resize(args.window_size),
+
+      Input::Button(ButtonArgs { button: Button::Keyboard(Key::LCtrl | Key::RCtrl), state, .. }) => {
+        self.ctrl = *state == ButtonState::Press;
+      }
+
+      Input::Button(ButtonArgs { button: Button::Keyboard(Key::LShift | Key::RShift), state, .. }) => {
+        self.shift = *state == ButtonState::Press;
+      }
+
+      Input::Button(ButtonArgs { button: Button::Keyboard(Key::LAlt | Key::RAlt), state, .. }) => {
+        self.alt = *state == ButtonState::Press;
+      }
+
+      Input::Button(ButtonArgs { button: Button::Keyboard(key), state: ButtonState::Press, .. }) => {
+        log::trace!(target: "input", "{:?} pressed", key);
+
+        if self.move_input.is_some() {
+          self.move_input_key(*key);
+        } else if self.setup.is_some() {
+          self.setup_key(*key);
+        } else {
+          self.key_press(*key);
+        }
+      }
+
+      Input::Text(text) => {
+        if let Some(move_input) = &mut self.move_input {
+          move_input.extend(text.chars().filter(|c| !c.is_control()));
+          self.move_input_invalid = false;
+        }
+      }
+
+      _ => {
+        self.mouse.handle_input(input);
+
+        /* right click cancels premoves before it draws anything */
+        if let Some((start, end)) = self.mouse.right_drag.take() {
+          if let Some(setup) = &mut self.setup {
+            if let Some(idx) = self.layout.square_at(start) {
+              setup.set(idx, None);
+            }
+          } else if self.premoves.is_empty() && self.input_board().current_select.is_none() {
+            self.annotate(start, end);
+          } else {
+            self.cancel_premoves();
+          }
+        }
+      }
+    }
+  }
+
+  /* right click marks a square, right drag draws an arrow, green by default,
+     red with shift, blue with alt and yellow with both */
+  fn annotate(&mut self, start: [f64; 2], end: [f64; 2]) {
+    let color = match (self.shift, self.alt) {
+      (false, false) => MarkColor::Green,
+      (true, false) => MarkColor::Red,
+      (false, true) => MarkColor::Blue,
+      (true, true) => MarkColor::Yellow
+    };
+
+    let annotations = &mut self.annotations[self.history_index];
+
+    match (self.layout.square_at(start), self.layout.square_at(end)) {
+      (Some(from), Some(to)) if from == to => annotations.toggle_square(color, from),
+      (Some(from), Some(to)) => annotations.toggle_arrow(color, from, to),
+      _ => {}
+    }
+  }
+
+  fn key_press(&mut self, key: Key) {
+    match key {
+      /* space asks the engine for a move, pressing it again plays the best one found so far */
+      Key::Space => {
+        if !self.engine.is_thinking() {
+          self.engine_move();
+        } else if let Some(mov) = self.engine.stop() {
+          self.play_move(mov);
+        }
+      }
+
+      Key::F => self.layout.flipped = !self.layout.flipped,
+
+      /* nobody, black, white, nobody... */
+      Key::E => {
+        let engine_color = match self.engine.color {
+          None => Some(Color::Black),
+          Some(Color::Black) => Some(Color::White),
+          Some(Color::White) => None
+        };
+
+        self.set_engine_color(engine_color);
+      }
+
+      Key::B => self.show_book = !self.show_book,
+      Key::H => self.hint(),
+      Key::L => self.show_coordinates = !self.show_coordinates,
+
+      Key::M => {
+        self.legal_move_style = match self.legal_move_style {
+          LegalMoveStyle::Dots => LegalMoveStyle::Squares,
+          LegalMoveStyle::Squares => LegalMoveStyle::Hidden,
+          LegalMoveStyle::Hidden => LegalMoveStyle::Dots
+        };
+      }
+      Key::T => self.next_theme(),
+
+      Key::A => {
+        self.animation_duration = if self.animation_duration > 0.0 { 0.0 } else { DEFAULT_ANIMATION_DURATION };
+      }
+      Key::P => self.next_piece_set(),
+
+      Key::C => {
+        self.time_control = (self.time_control + 1) % TIME_CONTROLS.len();
+        self.set_time_control(TimeControl::parse(TIME_CONTROLS[self.time_control]));
+      }
+
+      Key::S if self.ctrl => {
+        match pgn::save(&self.game(), Path::new(PGN_PATH)) {
+          Ok(()) => log::info!(target: "rules", "saved the game to {}", PGN_PATH),
+          Err(err) => log::error!(target: "rules", "couldn't save {}: {}", PGN_PATH, err)
+        }
+      }
+
+      Key::G if self.ctrl => self.export_gif(),
+
+      Key::O if self.ctrl => {
+        match pgn::load(Path::new(PGN_PATH)) {
+          Ok(game) => self.load_game(game),
+          Err(err) => log::error!(target: "rules", "couldn't open {}: {}", PGN_PATH, err)
+        }
+      }
+
+      Key::S if !self.ctrl => {
+        self.engine.stop();
+
+        self.board.deselect();
+        self.cancel_premoves();
+        self.setup = Some(Setup::new(&self.board));
+      }
+
+      Key::Z if self.ctrl => self.view(self.history_index.saturating_sub(1)),
+      Key::Y if self.ctrl => self.view(self.history_index + 1),
+
+      /* the arrows browse the history unless the keyboard cursor is out, page up and down always do */
+      Key::Left | Key::Right | Key::Up | Key::Down if self.cursor.is_some() => self.move_cursor(key),
+      Key::Left | Key::PageUp => self.view(self.history_index.saturating_sub(1)),
+      Key::Right | Key::PageDown => self.view(self.history_index + 1),
+      Key::Up | Key::Home => self.view(0),
+      Key::Down | Key::End => self.view(self.history.len() - 1),
+
+      /* the first enter shows the cursor on the e pawn at the bottom */
+      Key::Return | Key::NumPadEnter if self.cursor.is_none() => {
+        self.cursor = Some(if self.layout.flipped { 63 - 38 } else { 38 });
+      }
+
+      /* after that enter works like clicking the square under the keyboard cursor */
+      Key::Return | Key::NumPadEnter => {
+        if let (Some(cursor), None) = (self.cursor, self.result) {
+          let position = self.layout.square_center(cursor);
+
+          if self.engine_to_move() {
+            let board = self.premove_board();
+            let ply = board.ply;
+
+            board.click(Some(cursor), position);
+            self.queue_premove(ply);
+          } else {
+            self.board.click(Some(cursor), position);
+          }
+        }
+      }
+
+      Key::Tab => {
+        self.move_input = Some(String::new());
+        self.move_input_invalid = false;
+      }
+
+      Key::Escape => {
+        if self.board.current_select.is_some() {
+          self.board.deselect();
+        } else if self.cursor.is_some() {
+          self.cursor = None;
+        } else {
+          self.quit = true;
+        }
+      }
+
+      _ => {}
+    }
+  }
+
+  /* W and B pick the side to move, 1 to 4 toggle KQkq castling and E steps through en passant squares */
+  fn setup_key(&mut self, key: Key) {
+    let setup = match &mut self.setup {
+      Some(setup) => setup,
+      None => return
+    };
+
+    match key {
+      Key::W => setup.set_color_to_move(Color::White),
+      Key::B => setup.set_color_to_move(Color::Black),
+      Key::D1 => setup.toggle_castling(0),
+      Key::D2 => setup.toggle_castling(1),
+      Key::D3 => setup.toggle_castling(2),
+      Key::D4 => setup.toggle_castling(3),
+      Key::E => setup.next_en_passant(),
+      Key::C => setup.clear(),
+      Key::R => *setup = Setup::new(&Board::new()),
+      Key::F => self.layout.flipped = !self.layout.flipped,
+      Key::Return | Key::NumPadEnter => self.finish_setup(false),
+      Key::A => self.finish_setup(true),
+      Key::Escape => self.setup = None,
+      _ => {}
+    }
+  }
+
+  /* analysis turns the engine off, otherwise it keeps playing the side it had */
+  fn finish_setup(&mut self, analysis: bool) {
+    let board = match self.setup.as_ref().map(Setup::finish) {
+      Some(Ok(board)) => board,
+      Some(Err(err)) => {
+        self.setup.as_mut().unwrap().error = Some(err);
+        return;
+      }
+      None => return
+    };
+
+    self.setup = None;
+
+    if analysis {
+      self.set_engine_color(None);
+    }
+
+    self.load_game(Game::new(board));
+    self.set_time_control(TimeControl::parse(TIME_CONTROLS[self.time_control]));
+  }
+
+  /* clicking a palette piece picks it as the brush, clicking a square puts it down or takes it off again,
+     pieces can also be dragged from the palette or around the board and off it */
+  fn setup_input(&mut self) {
+    let setup = match &mut self.setup {
+      Some(setup) => setup,
+      None => return
+    };
+
+    if let Some(position) = self.mouse.clicked {
+      match (setup::palette_at(&self.layout, position), self.layout.square_at(position)) {
+        (Some(piece), _) => {
+          let same = setup.brush.is_some_and(|brush| setup::same_piece(brush, piece));
+          setup.brush = if same { None } else { Some(piece) };
+        }
+
+        (None, Some(idx)) => {
+          let same = matches!((setup.brush, setup.piece_at(idx)), (Some(brush), Some(piece)) if setup::same_piece(brush, piece));
+          setup.set(idx, if same { None } else { setup.brush });
+        }
+
+        _ => {}
+      }
+
+      self.mouse.reset_drag();
+    } else if self.mouse.drag_completed {
+      if let (Some(start), Some(end)) = (self.mouse.start, self.mouse.end) {
+        let piece = setup::palette_at(&self.layout, start).or_else(|| {
+          let idx = self.layout.square_at(start)?;
+          let piece = setup.piece_at(idx);
+
+          setup.set(idx, None);
+          piece
+        });
+
+        if let (Some(piece), Some(idx)) = (piece, self.layout.square_at(end)) {
+          setup.set(idx, Some(piece));
+        }
+      }
+
+      self.mouse.reset_drag();
+    }
+  }
+
+  /* the arrows move on screen, so up is always away from the side at the bottom */
+  fn move_cursor(&mut self, key: Key) {
+    let flip = |idx: usize| if self.layout.flipped { 63 - idx } else { idx };
+
+    let cursor = match self.cursor {
+      Some(cursor) => flip(cursor),
+      None => return
+    };
+
+    let (x, y) = (cursor / 8, cursor % 8);
+
+    let (x, y) = match key {
+      Key::Left => (x.saturating_sub(1), y),
+      Key::Right => ((x + 1).min(7), y),
+      Key::Up => (x, y.saturating_sub(1)),
+      Key::Down => (x, (y + 1).min(7)),
+      _ => (x, y)
+    };
+
+    self.cursor = Some(flip(x * 8 + y));
+  }
+
+  /* keys go to the move box while it's open instead of being hotkeys */
+  fn move_input_key(&mut self, key: Key) {
+    match key {
+      Key::Return | Key::NumPadEnter => self.submit_move_input(),
+
+      Key::Backspace => {
+        if let Some(move_input) = &mut self.move_input {
+          move_input.pop();
+        }
+
+        self.move_input_invalid = false;
+      }
+
+      Key::Escape | Key::Tab => self.move_input = None,
+
+      _ => {}
+    }
+  }
+
+  /* plays a typed san or uci move, the box stays open for the next one until enter is pressed on an empty box */
+  fn submit_move_input(&mut self) {
+    let text = self.move_input.clone().unwrap_or_default();
+
+    if text.trim().is_empty() {
+      self.move_input = None;
+      return;
+    }
+
+    if self.result.is_some() || self.engine_to_move() {
+      self.move_input_invalid = true;
+      return;
+    }
+
+    self.board.deselect();
+
+    match notation::parse_move(&self.board, &text) {
+      Some(mov) => {
+        self.engine.stop();
+
+        self.play_move(mov);
+        self.move_input = Some(String::new());
+      }
+
+      None => {
+        log::debug!(target: "input", "'{}' isn't a legal move", text);
+        self.move_input_invalid = true;
+      }
+    }
+  }
+
+  fn engine_move(&mut self) {
+    if self.board.current_select.is_some() || self.result.is_some() { return; }
+
+    /* book moves are played straight away */
+    if let Some(mov) = self.engine.think(&self.board) {
+      self.play_move(mov);
+    }
+  }
+
+  /* the engine plays color on its own, the board turns so the human side is at the bottom */
+  pub fn set_engine_color(&mut self, color: Option<Color>) {
+    self.board.deselect();
+    self.cancel_premoves();
+    self.engine.color = color;
+    self.layout.flipped = color == Some(Color::White);
+  }
+
+  /* shows an earlier or later position, moving from there starts a new line */
+  fn view(&mut self, index: usize) {
+    if index >= self.history.len() || self.board.current_select.is_some() { return; }
+
+    self.engine.stop();
+
+    self.cancel_premoves();
+
+    /* stepping one move either way slides the pieces, jumping further doesn't */
+    self.animation = if index.abs_diff(self.history_index) == 1 {
+      self.animation_between(&self.history[self.history_index], &self.history[index])
+    } else {
+      None
+    };
+
+    self.history_index = index;
+    self.board = self.history[index].clone();
+    self.clock_ply = self.board.ply;
+    self.update_highlights();
+
+    if let Some(clock) = &mut self.clock {
+      clock.set_turn(self.board.color_to_move);
+    }
+  }
+
+  /* the last move and a king in check, the hint is for the old position so it goes */
+  fn update_highlights(&mut self) {
+    match self.board.last_move {
+      Some(mov) => self.highlights.set(Layer::LastMove, &[mov.start, mov.target]),
+      None => self.highlights.clear(Layer::LastMove)
+    }
+
+    let color = self.board.color_to_move;
+    let king = (0 .. 8 * 8).find(|idx| {
+      self.board.board[*idx].piece.is_some_and(|piece| piece.piece == PieceType::King && piece.color == color)
+    });
+
+    match king.filter(|_| is_in_check(&self.board, color)) {
+      Some(king) => self.highlights.set(Layer::Check, &[king]),
+      None => self.highlights.clear(Layer::Check)
+    }
+
+    self.highlights.clear(Layer::Hint);
+
+    if let Some(search) = self.hint_search.take() {
+      search.stop();
+    }
+  }
+
+  /* tablebases and the book answer straight away, otherwise the engine looks for a move */
+  fn hint(&mut self) {
+    if self.board.current_select.is_some() || self.hint_search.is_some() { return; }
+
+    let book_move = self.engine.book.as_ref().and_then(|book| {
+      book.moves(&self.board).into_iter().max_by_key(|(_, weight)| *weight).map(|(mov, _)| mov)
+    });
+
+    match self.engine.tablebase.as_ref().and_then(|tablebase| tablebase.best_move(&self.board)).or(book_move) {
+      Some(mov) => self.highlights.set(Layer::Hint, &[mov.start, mov.target]),
+      None => self.hint_search = Some(self.engine.search(&self.board))
+    }
+  }
+
+  /* the moves played so far and the marks drawn on them */
+  pub fn game(&self) -> Game {
+    Game { positions: self.history.clone(), annotations: self.annotations.clone(), result: self.result }
+  }
+
+  /* Ctrl+G, the game so far as seen on screen, encoded off the gui thread since long games take a while,
+     every export gets its own game-<unix millis>.gif next to game.pgn so earlier ones stay */
+  fn export_gif(&self) {
+    let (theme, piece_set, game) = (self.theme.clone(), self.sprite_handler.piece_set.clone(), self.game());
+    let options = GifOptions { flipped: self.layout.flipped, coordinates: self.show_coordinates, ..self.gif_options.clone() };
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or(0);
+    let path = PathBuf::from(format!("game-{}.gif", time));
+
+    thread::spawn(move || {
+      let result = Renderer::new(theme, &piece_set, DEFAULT_SQUARE as u32)
+        .and_then(|renderer| render::save_gif(&renderer, &game, &options, &path).map_err(|err| err.to_string()));
+
+      match result {
+        Ok(()) => log::info!(target: "rendering", "saved the game to {}", path.display()),
+        Err(err) => log::error!(target: "rendering", "couldn't save {}: {}", path.display(), err)
+      }
+    });
+  }
+
+  /* replaces the game, showing its last position */
+  pub fn load_game(&mut self, game: Game) {
+    self.engine.stop();
+
+    self.board.deselect();
+    self.cancel_premoves();
+    self.history = game.positions;
+    self.annotations = game.annotations;
+    self.history_index = self.history.len() - 1;
+    self.board = self.history[self.history_index].clone();
+    self.result = game.result;
+    self.animation = None;
+    self.clock_ply = self.board.ply;
+    self.update_highlights();
+
+    if let Some(clock) = &mut self.clock {
+      clock.set_turn(self.board.color_to_move);
+    }
+  }
+
+  fn premove_board(&mut self) -> &mut Board {
+    if self.premove_board.is_none() {
+      let mut board = self.board.clone();
+      board.color_to_move.reverse();
+
+      for mov in &self.premoves {
+        board.make_move(*mov);
+        board.color_to_move.reverse();
+      }
+
+      self.premove_board = Some(board);
+    }
+
+    self.premove_board.as_mut().unwrap()
+  }
+
+  /* picks up a move made on the premove board since it was at ply */
+  fn queue_premove(&mut self, ply: usize) {
+    if let Some(board) = &mut self.premove_board {
+      if board.ply == ply { return; }
+
+      /* still the human's turn there */
+      board.color_to_move.reverse();
+      self.premoves.extend(board.last_move);
+      self.update_premove_highlight();
+    }
+  }
+
+  /* the next premove is played if it's legal now, otherwise the whole queue goes */
+  fn play_premove(&mut self) {
+    if self.premoves.is_empty() || self.board.current_select.is_some() { return; }
+
+    let premove = self.premoves.remove(0);
+
+    match legal_moves(&self.board).into_iter().find(|mov| mov.start == premove.start && mov.target == premove.target) {
+      Some(mov) => self.play_move(mov),
+      None => self.premoves.clear()
+    }
+
+    self.premove_board = None;
+    self.update_premove_highlight();
+  }
+
+  pub fn cancel_premoves(&mut self) {
+    if let Some(board) = &mut self.premove_board {
+      if board.current_select.is_some() {
+        board.deselect();
+        self.mouse.reset_drag();
+      }
+    }
+
+    self.premoves.clear();
+    self.premove_board = None;
+    self.update_premove_highlight();
+  }
+
+  fn update_premove_highlight(&mut self) {
+    let squares = self.premoves.iter().flat_map(|mov| [mov.start, mov.target]).collect::<Vec<_>>();
+
+    self.highlights.set(Layer::Premove, &squares);
+  }
+
+  /* the board a piece is being picked up on, the premove board during the engine's turn */
+  fn input_board(&self) -> &Board {
+    match &self.premove_board {
+      Some(board) if board.current_select.is_some() => board,
+      _ => &self.board
+    }
+  }
+
+  fn animation_between(&self, before: &Board, after: &Board) -> Option<Animation> {
+    if self.animation_duration <= 0.0 { return None; }
+
+    Animation::between(before, after, self.animation_duration)
+  }
+
+  fn next_theme(&mut self) {
+    if self.themes.is_empty() { return; }
+
+    self.theme_index = (self.theme_index + 1) % self.themes.len();
+
+    let path = &self.themes[self.theme_index];
+
+    match Theme::load(path) {
+      Ok(theme) => {
+        log::info!(target: "rendering", "theme {}", path.display());
+        self.theme = theme;
+      }
+
+      Err(err) => log::warn!(target: "rendering", "couldn't load theme {}: {}", path.display(), err)
+    }
+  }
+
+  fn next_piece_set(&mut self) {
+    if self.piece_sets.is_empty() { return; }
+
+    self.piece_set_index = (self.piece_set_index + 1) % self.piece_sets.len();
+    let path = &self.piece_sets[self.piece_set_index];
+
+    let result = PieceSet::load(path).map_err(|err| err.to_string()).and_then(|piece_set| {
+      self.sprite_handler.set_piece_set(piece_set, self.layout.square as u32)
+    });
+
+    match result {
+      Ok(()) => log::info!(target: "rendering", "piece set {}", path.display()),
+      Err(err) => log::warn!(target: "rendering", "couldn't load piece set {}: {}", path.display(), err)
+    }
+  }
+
+  pub fn resize(&mut self, window_size: [f64; 2]) {
+    let layout = Layout::new(window_size, self.layout.flipped);
+
+    if layout.square != self.layout.square {
+      match self.sprite_handler.load(layout.square as u32) {
+        Ok(()) => log::debug!(target: "rendering", "sprites redrawn at {} pixels", layout.square),
+        Err(err) => log::warn!(target: "rendering", "couldn't redraw the sprites at {} pixels: {}", layout.square, err)
+      }
+    }
+
+    self.layout = layout;
+  }
+
+  /* starts a new clock for the side to move, None plays untimed */
+  pub fn set_time_control(&mut self, control: Option<TimeControl>) {
+    self.clock = control.map(|control| Clock::new(control, self.board.color_to_move));
+    self.clock_ply = self.board.ply;
+    self.result = None;
+  }
+
+  /* runs on update events, time doesn't pass while the window is out of focus */
+  pub fn update(&mut self, dt: f64) {
+    if self.animation.as_mut().is_some_and(|animation| animation.tick(dt)) {
+      self.animation = None;
+    }
+
+    if self.paused || self.result.is_some() || self.setup.is_some() { return; }
+
+    if let Some(clock) = &mut self.clock {
+      clock.tick(Duration::from_secs_f64(dt));
+      self.result = clock.timeout_result(&self.board);
+
+      if let (Some(result), Some(color)) = (self.result, clock.flagged) {
+        log::info!(target: "rules", "{:?} ran out of time, {:?}", color, result);
+        self.engine.stop();
+      }
     }
   }
 
   pub fn game_update(&mut self) {
-    if self.paused { return; }
+    if self.setup.is_some() {
+      self.setup_input();
+      return;
+    }
+
+    if self.paused || self.result.is_some() { return; }
+
+    if let Some(search) = self.hint_search.take() {
+      if !search.is_finished() {
+        self.hint_search = Some(search);
+      } else if let Some(mov) = search.wait() {
+        log::debug!(target: "engine", "hint {}", notation::to_san(&self.board, mov));
+        self.highlights.set(Layer::Hint, &[mov.start, mov.target]);
+      }
+    }
+
+    if let Some(mov) = self.engine.finished_move(&self.board) {
+      self.play_move(mov);
+    }
+
+    /* a dragged piece is already where it was dropped, so that move isn't animated */
+    let mut dropped = false;
+
+    if self.engine_to_move() {
+      self.engine_move();
+    }
+
+    /* the mouse picks premoves while the engine thinks, a book move may have been played already */
+    if self.engine_to_move() {
+      let ply = self.premove_board().ply;
+
+      if let Some(board) = &mut self.premove_board {
+        board.select(&mut self.mouse, &self.layout);
+      }
+
+      self.queue_premove(ply);
+    } else if !self.engine.is_thinking() {
+      self.play_premove();
+
+      dropped = self.mouse.drag_completed;
+      self.board.select(&mut self.mouse, &self.layout);
+    }
+
+    self.update_tablebase_result();
+
+    if self.board.ply != self.history[self.history_index].ply {
+      if let Some(mov) = self.board.last_move {
+        log::info!(target: "rules", "{} played, {}", notation::to_san(&self.history[self.history_index], mov), self.board.to_fen());
+      }
+
+      self.animation = if dropped { None } else { self.animation_between(&self.history[self.history_index], &self.board) };
+
+      self.history.truncate(self.history_index + 1);
+      self.history.push(self.board.clone());
+      self.annotations.truncate(self.history_index + 1);
+      self.annotations.push(Annotations::default());
+      self.history_index += 1;
+      self.update_highlights();
 
-    self.board.select(&mut self.mouse);
+      /* a premove being dragged carries on as a normal move */
+      self.premove_board = None;
+    }
+
+    if self.board.ply != self.clock_ply {
+      self.clock_ply = self.board.ply;
+
+      if let Some(clock) = &mut self.clock {
+        clock.press();
+      }
+    }
   }
 
-  fn get_color(&self, rank: i32, file: i32) -> [f32; 4] {
-    let tile = self.board.get_tile_at((rank * 8 + file) as usize);
+  /* the engine only moves on its own from the latest position, not while browsing */
+  fn engine_to_move(&self) -> bool {
+    self.engine.to_move(&self.board) && self.history_index + 1 == self.history.len()
+  }
+
+  fn update_tablebase_result(&mut self) {
+    /* a lifted piece is missing from the board */
+    if self.board.current_select.is_some() { return; }
+
+    let key = zobrist::hash(&self.board);
+    if key == self.tablebase_key { return; }
 
-    if tile.selected == true {
-      return SELECTED_SQUARE;
+    self.tablebase_key = key;
+    self.tablebase_result = self.engine.tablebase.as_ref().and_then(|tablebase| tablebase.describe(&self.board));
+  }
+
+  pub fn title(&self) -> String {
+    match &self.tablebase_result {
+      Some(result) => format!("Chess - tablebase result: {}", result),
+      None => String::from("Chess")
     }
+  }
 
+  fn play_move(&mut self, mov: Move) {
+    self.board.make_move(mov);
+  }
+
+  fn get_color(&self, rank: i32, file: i32) -> [f32; 4] {
+    let idx = (rank * 8 + file) as usize;
     let is_light_square = (rank + file) % 2 == 0;
 
-    return if is_light_square { LIGHT_SQUARE } else { DARK_SQUARE };
+    if self.setup.is_some() {
+      return if is_light_square { self.theme.light_square } else { self.theme.dark_square };
+    }
+
+    if self.input_board().current_select.is_some_and(|select| select.origin == idx) {
+      return self.theme.selected_square;
+    }
+
+    match self.highlights.top(idx) {
+      Some(Layer::LastMove) => return self.theme.last_move,
+      Some(Layer::Check) => return self.theme.check,
+      Some(Layer::Premove) => return self.theme.premove,
+      Some(Layer::Hint) => return self.theme.hint,
+      None => {}
+    }
+
+    return if is_light_square { self.theme.light_square } else { self.theme.dark_square };
   }
 
   pub fn draw_board(&self, ctx: Context, graphics: &mut G2d) {
     for file in 0 .. 8 {
       for rank in 0 .. 8 {
         let color = self.get_color(rank, file);
-        let (x, y) = (rank as f64 * PIECE_HEIGHT, file as f64 * PIECE_WIDTH);
+        let (x, y) = self.layout.square_pos((rank * 8 + file) as usize);
 
         rectangle(
           color,
-          [x, y, PIECE_WIDTH, PIECE_HEIGHT],
+          [x, y, self.layout.square, self.layout.square],
           ctx.transform,
           graphics
         );
 
-        if let Some(piece) = self.board.get_piece_at((rank * 8 + file) as usize) {
+        let idx = (rank * 8 + file) as usize;
+        let premove_origin = self.premove_board.as_ref().and_then(|board| board.current_select).is_some_and(|select| select.origin == idx);
+        let hidden = premove_origin || self.animation.as_ref().is_some_and(|animation| animation.hides(idx));
+
+        let piece = match &self.setup {
+          /* a piece dragged in the editor leaves its square straight away */
+          Some(_) if self.mouse.started_drag && self.mouse.start.and_then(|start| self.layout.square_at(start)) == Some(idx) => None,
+          Some(setup) => setup.piece_at(idx),
+          None => self.board.get_piece_at(idx)
+        };
+
+        if let Some(piece) = piece.filter(|_| !hidden) {
           let idx = piece.sprite_sheet_pos();
           let sprite_image = self.sprite_handler.sprites.get(idx).unwrap();
           let transform = ctx.transform.trans(x, y);
@@ -91,51 +938,314 @@ impl <'a>GameState<'a> {
     }
   }
 
-  pub fn draw_selected_piece(&self, ctx: Context, graphics: &mut G2d) {
-    match (self.board.current_select, self.mouse.current) {
-      (Some(selected_piece), Some(current)) => {
-        self.draw_legal_moves(ctx, graphics);
+  /* moving pieces on top of the board, taken ones fading out under them */
+  pub fn draw_animation(&self, ctx: Context, graphics: &mut G2d) {
+    if let Some(animation) = &self.animation {
+      let t = animation.progress();
 
-        let sprite_image = self.sprite_handler.sprites.get(selected_piece.piece.sprite_sheet_pos()).unwrap();
-        let transform = ctx.transform.trans(current[0], current[1]);
+      for (piece, idx) in &animation.captured {
+        let (x, y) = self.layout.square_pos(*idx);
+        let sprite_image = self.sprite_handler.sprites.get(piece.sprite_sheet_pos()).unwrap();
+
+        Image::new_color([1.0, 1.0, 1.0, (1.0 - t) as f32]).draw(sprite_image, &ctx.draw_state, ctx.transform.trans(x, y), graphics);
+      }
+
+      for (piece, from, to) in &animation.moving {
+        let ((from_x, from_y), (to_x, to_y)) = (self.layout.square_pos(*from), self.layout.square_pos(*to));
+        let sprite_image = self.sprite_handler.sprites.get(piece.sprite_sheet_pos()).unwrap();
+        let transform = ctx.transform.trans(from_x + (to_x - from_x) * t, from_y + (to_y - from_y) * t);
 
         image(sprite_image, transform, graphics);
       }
+    }
+  }
 
-      _ => {}
+  /* marked squares and arrows on the position being shown */
+  pub fn draw_annotations(&self, ctx: Context, graphics: &mut G2d) {
+    if self.setup.is_some() { return; }
+
+    let annotations = &self.annotations[self.history_index];
+    let square = self.layout.square;
+
+    for (mark, idx) in &annotations.squares {
+      let (x, y) = self.layout.square_pos(*idx);
+      let mut fill = self.theme.annotation(*mark);
+      fill[3] *= 0.6;
+
+      rectangle(fill, [x, y, square, square], ctx.transform, graphics);
+    }
+
+    for (mark, from, to) in &annotations.arrows {
+      let (shaft, head) = annotation::arrow_polygons(self.layout.square_center(*from), self.layout.square_center(*to), square);
+
+      polygon(self.theme.annotation(*mark), &shaft, ctx.transform, graphics);
+      polygon(self.theme.annotation(*mark), &head, ctx.transform, graphics);
     }
   }
 
-  pub fn draw_legal_moves(&self, ctx: Context, graphics: &mut G2d) {
-    match self.board.current_select {
-      Some(_) => {
-        for mov in &self.board.moves {
-          // cant think of a better way rn
-          for file in 0 .. 8 {
-            for rank in 0 .. 8 {
-              let target = rank * 8 + file;
-
-              if target == mov.target {
-                let color = if (rank + file) % 2 == 0 { LEGAL_LIGHT_SQUARE } else { LEGAL_DARK_SQUARE };
-
-                let (x, y) = (rank as f64 * PIECE_HEIGHT, file as f64 * PIECE_WIDTH);
-
-                rectangle(
-                  color,
-                  [x, y, PIECE_WIDTH, PIECE_HEIGHT],
-                  ctx.transform,
-                  graphics
-                );
-
-                break;
-              }
-            }
-          }
+  /* files along the bottom edge and ranks along the left one, whichever way the board is turned */
+  pub fn draw_coordinates(&self, ctx: Context, graphics: &mut G2d, glyphs: &mut Glyphs) {
+    if !self.show_coordinates { return; }
+
+    let size = self.layout.font_size(13.0);
+
+    for (idx, label, [x, y]) in self.layout.coordinate_labels(size as f64) {
+      /* in the color of the other squares so they read on both */
+      let color = if (idx / 8 + idx % 8) % 2 == 0 { self.theme.dark_square } else { self.theme.light_square };
+
+      text::Text::new_color(color, size).draw(
+        &label,
+        glyphs,
+        &ctx.draw_state,
+        ctx.transform.trans(x, y),
+        graphics
+      ).ok();
+    }
+  }
+
+  /* arrows for every book move, thicker for more popular moves */
+  pub fn draw_book_moves(&self, ctx: Context, graphics: &mut G2d) {
+    if !self.show_book || self.setup.is_some() { return; }
+
+    if let Some(book) = &self.engine.book {
+      let moves = book.moves(&self.board);
+      let total = moves.iter().map(|(_, weight)| *weight as f64).sum::<f64>().max(1.0);
+
+      for (mov, weight) in moves {
+        let (start, target) = (self.layout.square_center(mov.start), self.layout.square_center(mov.target));
+        let radius = 2.0 + self.layout.square / 8.0 * (weight as f64 / total);
+
+        line_from_to(self.theme.book_move, radius, start, target, ctx.transform, graphics);
+        ellipse(self.theme.book_move, ellipse::circle(target[0], target[1], radius * 2.0), ctx.transform, graphics);
+      }
+    }
+  }
+
+  /* the palette and the position's settings cover the panel while editing */
+  pub fn draw_setup(&self, ctx: Context, graphics: &mut G2d, glyphs: &mut Glyphs) {
+    let setup = match &self.setup {
+      Some(setup) => setup,
+      None => return
+    };
+
+    let panel = self.layout.panel();
+    let [x, y, _, _] = panel;
+    let square = self.layout.square;
+
+    rectangle(self.theme.panel, panel, ctx.transform, graphics);
+
+    let mut bottom = y;
+
+    for (piece, [cell_x, cell_y, size]) in setup::palette(&self.layout) {
+      if setup.brush.is_some_and(|brush| setup::same_piece(brush, piece)) {
+        rectangle(self.theme.selected_square, [cell_x, cell_y, size, size], ctx.transform, graphics);
+      }
+
+      let sprite_image = self.sprite_handler.sprites.get(piece.sprite_sheet_pos()).unwrap();
+      image(sprite_image, ctx.transform.trans(cell_x, cell_y).scale(size / square, size / square), graphics);
+
+      bottom = cell_y + size;
+    }
+
+    let castling = "KQkq".chars().zip(setup.castling).filter(|(_, right)| *right).map(|(c, _)| c).collect::<String>();
+
+    let mut lines = vec![
+      (self.theme.panel_text, format!("W/B: {} to move", if setup.board.color_to_move == Color::White { "white" } else { "black" })),
+      (self.theme.panel_text, format!("1-4: castling {}", if castling.is_empty() { "-" } else { &castling })),
+      (self.theme.panel_text, format!("E: en passant {}", setup.en_passant.map(board::square_name).unwrap_or_else(|| String::from("-")))),
+      (self.theme.panel_text, String::from("C: clear  R: reset")),
+      (self.theme.panel_text, String::from("Enter: play  A: analyse"))
+    ];
+
+    /* errors are wrapped to fit the panel */
+    if let Some(error) = &setup.error {
+      let mut line = String::new();
+
+      for word in error.split(' ') {
+        if !line.is_empty() && line.len() + word.len() >= 24 {
+          lines.push((self.theme.clock_flagged, line.clone()));
+          line.clear();
         }
 
+        if !line.is_empty() { line.push(' '); }
+        line.push_str(word);
       }
 
-      _ => {}
+      lines.push((self.theme.clock_flagged, line));
+    }
+
+    for (i, (color, line)) in lines.iter().enumerate() {
+      text::Text::new_color(*color, self.layout.font_size(13.0)).draw(
+        line,
+        glyphs,
+        &ctx.draw_state,
+        ctx.transform.trans(x + square / 4.0, bottom + square * (0.5 + i as f64 * 0.3)),
+        graphics
+      ).ok();
+    }
+
+    /* the piece being dragged, from the palette or the board */
+    if let (true, Some(start), Some(current)) = (self.mouse.started_drag, self.mouse.start, self.mouse.current) {
+      let piece = setup::palette_at(&self.layout, start).or_else(|| setup.piece_at(self.layout.square_at(start)?));
+
+      if let Some(piece) = piece {
+        let sprite_image = self.sprite_handler.sprites.get(piece.sprite_sheet_pos()).unwrap();
+        image(sprite_image, ctx.transform.trans(current[0], current[1]), graphics);
+      }
     }
   }
-}
\ No newline at end of file
+
+  /* next to the board, the clock of the side at the bottom is at the bottom */
+  pub fn draw_clock(&self, ctx: Context, graphics: &mut G2d, glyphs: &mut Glyphs) {
+    let panel = self.layout.panel();
+    let [x, y, width, height] = panel;
+    let square = self.layout.square;
+
+    rectangle(self.theme.panel, panel, ctx.transform, graphics);
+
+    let (top, bottom) = if self.layout.flipped { (Color::White, Color::Black) } else { (Color::Black, Color::White) };
+
+    if let Some(clock) = &self.clock {
+      for (color, y) in [(top, y + square / 4.0), (bottom, y + height - square * 1.25)] {
+        let background = if clock.flagged == Some(color) {
+          self.theme.clock_flagged
+        } else if clock.turn == color && self.result.is_none() {
+          self.theme.clock_running
+        } else {
+          self.theme.clock_idle
+        };
+
+        rectangle(background, [x + square / 4.0, y, width - square / 2.0, square], ctx.transform, graphics);
+
+        text::Text::new_color(self.theme.panel_text, self.layout.font_size(28.0)).draw(
+          &clock::format_time(clock.remaining(color)),
+          glyphs,
+          &ctx.draw_state,
+          ctx.transform.trans(x + square * 0.44, y + square * 0.66),
+          graphics
+        ).ok();
+      }
+    }
+
+    if let Some(result) = self.result {
+      let message = match result {
+        GameResult::Win(Color::White) => "White wins",
+        GameResult::Win(Color::Black) => "Black wins",
+        GameResult::Draw => "Draw"
+      };
+
+      text::Text::new_color(self.theme.panel_text, self.layout.font_size(20.0)).draw(
+        message,
+        glyphs,
+        &ctx.draw_state,
+        ctx.transform.trans(x + square / 4.0, y + height / 2.0 + square * 0.11),
+        graphics
+      ).ok();
+    }
+  }
+
+  pub fn draw_cursor(&self, ctx: Context, graphics: &mut G2d) {
+    if let Some(cursor) = self.cursor {
+      let (x, y) = self.layout.square_pos(cursor);
+      let radius = (self.layout.square / 32.0).max(1.0);
+
+      Rectangle::new_border(self.theme.cursor, radius).draw(
+        [x + radius, y + radius, self.layout.square - radius * 2.0, self.layout.square - radius * 2.0],
+        &ctx.draw_state,
+        ctx.transform,
+        graphics
+      );
+    }
+  }
+
+  /* in the middle of the panel, under the result */
+  pub fn draw_move_input(&self, ctx: Context, graphics: &mut G2d, glyphs: &mut Glyphs) {
+    if let Some(move_input) = &self.move_input {
+      let [x, y, width, height] = self.layout.panel();
+      let square = self.layout.square;
+      let y = y + height / 2.0 + square * 0.4;
+
+      let background = if self.move_input_invalid { self.theme.move_input_invalid } else { self.theme.move_input };
+      rectangle(background, [x + square / 4.0, y, width - square / 2.0, square * 0.6], ctx.transform, graphics);
+
+      text::Text::new_color(self.theme.panel_text, self.layout.font_size(20.0)).draw(
+        &format!("{}_", move_input),
+        glyphs,
+        &ctx.draw_state,
+        ctx.transform.trans(x + square * 0.35, y + square * 0.42),
+        graphics
+      ).ok();
+    }
+  }
+
+  /* under the cursor while dragging, on its square when picked with a click */
+  pub fn draw_selected_piece(&self, ctx: Context, graphics: &mut G2d) {
+    if let Some(selected_piece) = self.input_board().current_select {
+      self.draw_legal_moves(ctx, graphics);
+
+      let (x, y) = match self.mouse.current {
+        Some(current) if self.mouse.started_drag => (current[0], current[1]),
+        _ => self.layout.square_pos(selected_piece.origin)
+      };
+
+      let sprite_image = self.sprite_handler.sprites.get(selected_piece.piece.sprite_sheet_pos()).unwrap();
+      let transform = ctx.transform.trans(x, y);
+
+      image(sprite_image, transform, graphics);
+    }
+  }
+
+  /* where the lifted piece can go, and the square it would be dropped on */
+  pub fn draw_legal_moves(&self, ctx: Context, graphics: &mut G2d) {
+    let board = self.input_board();
+    if board.current_select.is_none() { return; }
+
+    let square = self.layout.square;
+
+    if let (true, Some(current)) = (self.mouse.started_drag, self.mouse.current) {
+      if let Some(idx) = self.layout.square_at(current) {
+        let (x, y) = self.layout.square_pos(idx);
+        let border = (square / 16.0).max(1.0);
+
+        Rectangle::new_border(self.theme.hover, border / 2.0).draw(
+          [x + border / 2.0, y + border / 2.0, square - border, square - border],
+          &ctx.draw_state,
+          ctx.transform,
+          graphics
+        );
+      }
+    }
+
+    for mov in &board.moves {
+      let (x, y) = self.layout.square_pos(mov.target);
+      let [center_x, center_y] = self.layout.square_center(mov.target);
+      let capture = board.board[mov.target].piece.is_some() || matches!(mov.special, SpecialMove::EnPassant(_));
+
+      match self.legal_move_style {
+        LegalMoveStyle::Dots if capture => {
+          let border = square / 12.0;
+
+          Ellipse::new_border(self.theme.legal_move, border / 2.0).draw(
+            ellipse::circle(center_x, center_y, square / 2.0 - border / 2.0),
+            &ctx.draw_state,
+            ctx.transform,
+            graphics
+          );
+        }
+
+        LegalMoveStyle::Dots => {
+          ellipse(self.theme.legal_move, ellipse::circle(center_x, center_y, square / 6.0), ctx.transform, graphics);
+        }
+
+        LegalMoveStyle::Squares => {
+          let (rank, file) = (mov.target / 8, mov.target % 8);
+          let color = if (rank + file) % 2 == 0 { self.theme.legal_light_square } else { self.theme.legal_dark_square };
+
+          rectangle(color, [x, y, square, square], ctx.transform, graphics);
+        }
+
+        LegalMoveStyle::Hidden => {}
+      }
+    }
+  }
+}
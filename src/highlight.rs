@@ -0,0 +1,36 @@
+/* square highlights that are only for show, each layer is set and cleared on its own.
+   marks drawn by the user live with the annotations */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Layer {
+  LastMove,
+  Check,
+  Premove,
+  Hint
+}
+
+/* later layers are drawn over earlier ones */
+const LAYERS: [Layer; 4] = [Layer::LastMove, Layer::Check, Layer::Premove, Layer::Hint];
+
+#[derive(Debug, Clone, Default)]
+pub struct Highlights {
+  layers: [Vec<usize>; LAYERS.len()]
+}
+
+impl Highlights {
+  pub fn set(&mut self, layer: Layer, squares: &[usize]) {
+    self.layers[layer as usize] = squares.to_vec();
+  }
+
+  pub fn clear(&mut self, layer: Layer) {
+    self.layers[layer as usize].clear();
+  }
+
+  pub fn squares(&self, layer: Layer) -> &[usize] {
+    &self.layers[layer as usize]
+  }
+
+  /* the layer that shows on a square */
+  pub fn top(&self, idx: usize) -> Option<Layer> {
+    LAYERS.iter().rev().find(|layer| self.squares(**layer).contains(&idx)).copied()
+  }
+}
@@ -0,0 +1,100 @@
+use crate::board::square_name;
+
+/* where the board and the side panel go in the window, recomputed on resize */
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+  pub square: f64,
+  pub board_x: f64,
+  pub board_y: f64,
+  /* black at the bottom */
+  pub flipped: bool
+}
+
+pub const DEFAULT_SQUARE: f64 = 64.0;
+/* the panel is this many squares wide */
+const PANEL_SQUARES: f64 = 2.8;
+
+impl Layout {
+  pub fn new(window_size: [f64; 2], flipped: bool) -> Self {
+    let [width, height] = window_size;
+
+    /* whole pixels so sprites are drawn 1:1 */
+    let square = (width / (8.0 + PANEL_SQUARES)).min(height / 8.0).floor().max(8.0);
+    let total_width = square * (8.0 + PANEL_SQUARES);
+
+    Layout {
+      square,
+      board_x: ((width - total_width) / 2.0).max(0.0).floor(),
+      board_y: ((height - square * 8.0) / 2.0).max(0.0).floor(),
+      flipped
+    }
+  }
+
+  pub fn window_size() -> [f64; 2] {
+    [DEFAULT_SQUARE * (8.0 + PANEL_SQUARES), DEFAULT_SQUARE * 8.0]
+  }
+
+  pub fn board_size(&self) -> f64 {
+    self.square * 8.0
+  }
+
+  /* x, y, width, height of the panel right of the board */
+  pub fn panel(&self) -> [f64; 4] {
+    [self.board_x + self.board_size(), self.board_y, self.square * PANEL_SQUARES, self.board_size()]
+  }
+
+  /* top left corner of a square on screen */
+  pub fn square_pos(&self, idx: usize) -> (f64, f64) {
+    let idx = if self.flipped { 63 - idx } else { idx };
+
+    (self.board_x + (idx / 8) as f64 * self.square, self.board_y + (idx % 8) as f64 * self.square)
+  }
+
+  pub fn square_center(&self, idx: usize) -> [f64; 2] {
+    let (x, y) = self.square_pos(idx);
+
+    [x + self.square / 2.0, y + self.square / 2.0]
+  }
+
+  /* the square under a point, None outside the board */
+  pub fn square_at(&self, position: [f64; 2]) -> Option<usize> {
+    let x = ((position[0] - self.board_x) / self.square).floor();
+    let y = ((position[1] - self.board_y) / self.square).floor();
+
+    if !(0.0 .. 8.0).contains(&x) || !(0.0 .. 8.0).contains(&y) {
+      return None;
+    }
+
+    let idx = x as usize * 8 + y as usize;
+
+    Some(if self.flipped { 63 - idx } else { idx })
+  }
+
+  /* files along the bottom edge and ranks along the left one, whichever way the board is turned,
+     as the square each label sits in, the label and where its baseline starts */
+  pub fn coordinate_labels(&self, font_size: f64) -> Vec<(usize, String, [f64; 2])> {
+    let flip = |idx: usize| if self.flipped { 63 - idx } else { idx };
+    let mut labels = Vec::new();
+
+    for i in 0 .. 8 {
+      /* bottom row and left column on screen */
+      for (idx, is_file) in [(flip(i * 8 + 7), true), (flip(i), false)] {
+        let name = square_name(idx);
+        let (x, y) = self.square_pos(idx);
+
+        labels.push(if is_file {
+          (idx, name[.. 1].to_string(), [x + self.square - font_size * 0.75, y + self.square - font_size * 0.3])
+        } else {
+          (idx, name[1 ..].to_string(), [x + self.square * 0.06, y + font_size * 1.05])
+        });
+      }
+    }
+
+    labels
+  }
+
+  /* scales font sizes picked for the default square size */
+  pub fn font_size(&self, size: f64) -> u32 {
+    (size * self.square / DEFAULT_SQUARE).round().max(1.0) as u32
+  }
+}
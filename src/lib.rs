@@ -0,0 +1,32 @@
+/* the game, its engine and the headless renderer, the chess binary is the window, terminal and command line on top */
+extern crate piston_window;
+extern crate image;
+
+pub mod piece;
+pub mod board;
+pub mod sprite;
+pub mod cursor;
+pub mod game_state;
+pub mod move_generator;
+pub mod zobrist;
+pub mod transposition;
+pub mod search;
+pub mod engine;
+pub mod book;
+pub mod tablebase;
+pub mod clock;
+pub mod layout;
+pub mod notation;
+pub mod theme;
+pub mod animation;
+pub mod annotation;
+pub mod pgn;
+pub mod highlight;
+pub mod fen;
+pub mod setup;
+pub mod assets;
+pub mod render;
+pub mod tui;
+pub mod logging;
+
+pub use render::{ render, save_png, Diagram };
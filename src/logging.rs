@@ -0,0 +1,91 @@
+use std::fs::{ File, OpenOptions };
+use std::io::{ self, Write };
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use log::{ LevelFilter, Log, Metadata, Record };
+
+/* what the game logs under, anything else comes from dependencies:
+   rules    moves played, positions and results
+   input    keys, clicks and typed moves
+   rendering themes, piece sets and exported images
+   engine   searches, the opening book and tablebases */
+pub const TARGETS: [&str; 4] = ["rules", "input", "rendering", "engine"];
+
+/* warnings and errors only unless asked for more */
+pub const DEFAULT_LEVELS: &str = "warn";
+
+/* one line per record on stderr or in a file */
+struct Logger {
+  default: LevelFilter,
+  targets: Vec<(String, LevelFilter)>,
+  file: Option<Mutex<File>>,
+  start: Instant
+}
+
+/* e.g. "info" or "warn,engine=debug,input=off", the last matching entry wins */
+pub fn parse_levels(spec: &str) -> Result<(LevelFilter, Vec<(String, LevelFilter)>), String> {
+  let mut default = LevelFilter::Warn;
+  let mut targets = Vec::new();
+
+  for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+    let level = |text: &str| text.parse::<LevelFilter>().map_err(|_| format!("'{}' isn't a log level", text));
+
+    match entry.split_once('=') {
+      Some((target, _)) if !TARGETS.contains(&target) => return Err(format!("unknown log target '{}', try {}", target, TARGETS.join(", "))),
+      Some((target, text)) => targets.push((target.to_string(), level(text)?)),
+      None => default = level(entry)?
+    }
+  }
+
+  Ok((default, targets))
+}
+
+impl Logger {
+  fn level(&self, target: &str) -> LevelFilter {
+    self.targets.iter().rev().find(|(name, _)| name == target).map_or(self.default, |(_, level)| *level)
+  }
+}
+
+impl Log for Logger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= self.level(metadata.target())
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) { return; }
+
+    let line = format!("{:8.3} {:5} {}: {}\n", self.start.elapsed().as_secs_f64(), record.level(), record.target(), record.args());
+
+    match &self.file {
+      Some(file) => { let _ = file.lock().unwrap().write_all(line.as_bytes()); }
+      None => { let _ = io::stderr().write_all(line.as_bytes()); }
+    }
+  }
+
+  fn flush(&self) {
+    if let Some(file) = &self.file {
+      let _ = file.lock().unwrap().flush();
+    }
+  }
+}
+
+/* installs the logger once at startup, a file is appended to so earlier runs stay */
+pub fn init(levels: &str, file: Option<&Path>) -> Result<(), String> {
+  let (default, targets) = parse_levels(levels)?;
+
+  let file = match file {
+    Some(path) => Some(OpenOptions::new().create(true).append(true).open(path).map_err(|err| format!("couldn't open {}: {}", path.display(), err))?),
+    None => None
+  };
+
+  /* the most verbose level anything is logged at, so the log macros can skip the rest cheaply */
+  let max = targets.iter().map(|(_, level)| *level).chain([default]).max().unwrap_or(LevelFilter::Off);
+
+  log::set_boxed_logger(Box::new(Logger { default, targets, file: file.map(Mutex::new), start: Instant::now() }))
+    .map_err(|err| err.to_string())?;
+  log::set_max_level(max);
+
+  Ok(())
+}
@@ -1,47 +1,308 @@
 extern crate piston_window;
 extern crate image;
 
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+
 use piston_window::*;
 
-mod piece;
-mod board;
-mod sprite;
-mod cursor;
-mod game_state;
-mod move_generator;
+mod cli;
+
+use chess::{ board, fen, logging, pgn, render, sprite };
+use chess::game_state::GameState;
+use chess::layout::{ Layout, DEFAULT_SQUARE };
+use chess::pgn::Game;
+use chess::piece::Color;
+use chess::render::Renderer;
+use chess::sprite::SpriteHandler;
+use chess::tablebase::{ Tablebase, TablebaseOptions };
+use chess::theme::{ PieceSet, Theme };
+use chess::tui::Tui;
+
+fn exit_with(message: String) -> ! {
+  eprintln!("{}", message);
+  process::exit(2);
+}
+
+/* the theme and pieces for render and gif, the gui's defaults when not given */
+fn load_assets(theme: &Option<PathBuf>, pieces: &Option<PathBuf>) -> (Theme, PieceSet) {
+  let theme = match theme {
+    Some(path) => Theme::load(path).unwrap_or_else(|err| exit_with(format!("couldn't load theme {}: {}", path.display(), err))),
+    None => Theme::default()
+  };
+
+  let piece_set = match pieces {
+    Some(path) => PieceSet::load(path).unwrap_or_else(|err| exit_with(format!("couldn't load piece set {}: {}", path.display(), err))),
+    None => PieceSet::default()
+  };
+
+  (theme, piece_set)
+}
+
+/* chess render ..., draws a diagram and exits */
+fn render_diagram(args: Vec<String>) {
+  let options = match cli::parse_render(args) {
+    Ok(options) => options,
+    Err(err) => exit_with(format!("{}\n\n{}", err, cli::RENDER_USAGE))
+  };
+
+  if options.help {
+    println!("{}", cli::RENDER_USAGE);
+    return;
+  }
+
+  logging::init(&options.log, options.log_file.as_deref()).unwrap_or_else(|err| exit_with(err));
+
+  let (theme, piece_set) = load_assets(&options.theme, &options.pieces);
+  let fen = options.fen.as_deref().unwrap_or(board::DEFAULT_FEN);
+  let image = chess::render(fen, &options.diagram, theme, &piece_set, options.size).unwrap_or_else(|err| exit_with(err));
+
+  if let Err(err) = chess::save_png(&image, &options.out) {
+    exit_with(format!("couldn't write {}: {}", options.out.display(), err));
+  }
+}
+
+/* chess gif ..., plays a pgn back in a gif and exits */
+fn export_gif(args: Vec<String>) {
+  let command = match cli::parse_gif(args) {
+    Ok(command) => command,
+    Err(err) => exit_with(format!("{}\n\n{}", err, cli::GIF_USAGE))
+  };
+
+  if command.help {
+    println!("{}", cli::GIF_USAGE);
+    return;
+  }
+
+  logging::init(&command.log, command.log_file.as_deref()).unwrap_or_else(|err| exit_with(err));
+
+  let game = pgn::load(&command.pgn).unwrap_or_else(|err| exit_with(format!("couldn't open {}: {}", command.pgn.display(), err)));
+  let (theme, piece_set) = load_assets(&command.theme, &command.pieces);
+  let renderer = Renderer::new(theme, &piece_set, command.size).unwrap_or_else(|err| exit_with(format!("couldn't open piece sheet: {}", err)));
+
+  if let Err(err) = render::save_gif(&renderer, &game, &command.gif, &command.out) {
+    exit_with(format!("couldn't write {}: {}", command.out.display(), err));
+  }
+}
+
+/* --fen or --pgn */
+fn starting_game(options: &cli::Options) -> Option<Game> {
+  if let Some(fen) = &options.fen {
+    Some(Game::new(fen::parse(fen).unwrap_or_else(|err| exit_with(format!("invalid fen: {}", err)))))
+  } else {
+    options.pgn.as_ref().map(|path| {
+      pgn::load(path).unwrap_or_else(|err| exit_with(format!("couldn't open {}: {}", path.display(), err)))
+    })
+  }
+}
+
+/* --tablebases, a directory given on purpose has to open */
+fn open_tablebase(options: &cli::Options) -> Option<Arc<Tablebase>> {
+  options.tablebases.as_ref().map(|path| {
+    let options = TablebaseOptions { path: path.clone(), ..TablebaseOptions::default() };
+
+    Arc::new(Tablebase::open(options).unwrap_or_else(|err| exit_with(format!("couldn't load tablebases {}: {}", path.display(), err))))
+  })
+}
+
+/* --play-as picks the human's side */
+fn engine_color(options: &cli::Options) -> Option<Color> {
+  options.play_as.map(|color| {
+    let mut engine = color;
+    engine.reverse();
+    engine
+  })
+}
+
+fn save_game(options: &cli::Options, game: &Game) {
+  if let Some(path) = &options.save_pgn {
+    match pgn::save(game, path) {
+      Ok(()) => log::info!(target: "rules", "saved the game to {}", path.display()),
+      Err(err) => log::error!(target: "rules", "couldn't save {}: {}", path.display(), err)
+    }
+  }
+}
+
+/* --tui, the same game without a window */
+fn play_in_terminal(options: cli::Options) {
+  let mut tui = Tui::new();
+
+  if let Some(path) = &options.theme {
+    tui.theme = Theme::load(path).unwrap_or_else(|err| exit_with(format!("couldn't load theme {}: {}", path.display(), err)));
+  }
+
+  if let Some(depth) = options.engine_depth {
+    tui.engine.options.depth = depth;
+  }
+
+  if let Some(threads) = options.threads {
+    tui.engine.options.threads = threads;
+  }
+
+  if let (Some(book), Some(selection)) = (&mut tui.engine.book, options.book_selection) {
+    book.options.selection = selection;
+  }
+
+  if let Some(tablebase) = open_tablebase(&options) {
+    tui.engine.tablebase = Some(tablebase);
+  }
+
+  if let Some(game) = starting_game(&options) {
+    tui.load_game(game);
+  }
+
+  if options.play_as.is_some() {
+    tui.set_engine_color(engine_color(&options));
+  }
+
+  if options.time_control.is_some() {
+    tui.set_time_control(options.time_control.clone());
+  }
 
-use sprite::SpriteHandler;
-use game_state::GameState;
+  if let Err(err) = tui.run() {
+    exit_with(format!("terminal error: {}", err));
+  }
 
-const PIECE_WIDTH: f64 = 64.0;
-const PIECE_HEIGHT: f64 = 64.0;
+  save_game(&options, &tui.game());
+  log::info!(target: "rules", "final position {}", tui.board.to_fen());
+}
 
 fn main() {
+  let mut args = env::args().skip(1).collect::<Vec<_>>();
+
+  let subcommand = args.first().filter(|arg| *arg == "render" || *arg == "gif").cloned();
+
+  if let Some(subcommand) = subcommand {
+    return if subcommand == "render" { render_diagram(args.split_off(1)) } else { export_gif(args.split_off(1)) };
+  }
+
+  let options = match cli::parse(args) {
+    Ok(options) => options,
+    Err(err) => exit_with(format!("{}\n\n{}", err, cli::USAGE))
+  };
+
+  if options.help {
+    println!("{}", cli::USAGE);
+    return;
+  }
+
+  /* anything on stderr would end up in the middle of the terminal frontend's screen */
+  let levels = if options.tui && options.log_file.is_none() { "off" } else { options.log.as_str() };
+  logging::init(levels, options.log_file.as_deref()).unwrap_or_else(|err| exit_with(err));
+
+  if options.tui {
+    return play_in_terminal(options);
+  }
+
+  /* anything given on the command line that can't be loaded stops us before a window opens */
+  let piece_set = match &options.pieces {
+    Some(path) => PieceSet::load(path).unwrap_or_else(|err| exit_with(format!("couldn't load piece set {}: {}", path.display(), err))),
+    None => PieceSet::default()
+  };
+
+  let sheet = sprite::open_sheet(&piece_set).unwrap_or_else(|err| exit_with(format!("couldn't open piece sheet: {}", err)));
+
+  let theme = options.theme.as_ref().map(|path| {
+    Theme::load(path).unwrap_or_else(|err| exit_with(format!("couldn't load theme {}: {}", path.display(), err)))
+  });
+
+  let tablebase = open_tablebase(&options);
+  let game = starting_game(&options);
+
   let mut window: PistonWindow =
-    WindowSettings::new("Chess", [PIECE_WIDTH * 8.0, PIECE_HEIGHT * 8.0])
-    .exit_on_esc(true).build().unwrap();
+    WindowSettings::new("Chess", Layout::window_size())
+    .exit_on_esc(false).build().unwrap();
+
+  let mut glyphs = Glyphs::from_bytes(
+    chess::assets::FONT,
+    window.create_texture_context(),
+    TextureSettings::new()
+  ).unwrap();
 
   let mut texture_ctx = window.create_texture_context();
-  let sprite_handler = SpriteHandler::new("assets/pieces.png", &mut texture_ctx);
+  let sprite_handler = SpriteHandler::new(piece_set, sheet, &mut texture_ctx, DEFAULT_SQUARE as u32)
+    .unwrap_or_else(|err| exit_with(format!("couldn't draw the pieces: {}", err)));
   let mut state = GameState::new(sprite_handler);
 
+  if let Some(theme) = theme {
+    state.theme = theme;
+  }
+
+  if let Some(depth) = options.engine_depth {
+    state.engine.options.depth = depth;
+  }
+
+  if let Some(threads) = options.threads {
+    state.engine.options.threads = threads;
+  }
+
+  if let (Some(book), Some(selection)) = (&mut state.engine.book, options.book_selection) {
+    book.options.selection = selection;
+  }
+
+  if let Some(tablebase) = tablebase {
+    state.engine.tablebase = Some(tablebase);
+  }
+
+  state.gif_options = options.gif.clone();
+
+  if let Some(game) = game {
+    state.load_game(game);
+  }
+
+  if options.play_as.is_some() {
+    state.set_engine_color(engine_color(&options));
+  }
+
+  if options.time_control.is_some() {
+    state.set_time_control(options.time_control.clone());
+  }
+
+  let mut title = state.title();
+
   while let Some(event) = window.next() {
     match &event {
       Event::Input(input, _) => {
         state.input_event(input);
       }
 
+      Event::Loop(Loop::Update(args)) => {
+        state.update(args.dt);
+      }
+
       _ => {}
     }
 
     state.game_update();
 
-    window.draw_2d(&event, |ctx, graphics, _device| {
+    if state.quit {
+      window.set_should_close(true);
+    }
+
+    if state.title() != title {
+      title = state.title();
+      window.set_title(title.clone());
+    }
+
+    window.draw_2d(&event, |ctx, graphics, device| {
       clear([1.0; 4], graphics);
       state.draw_board(ctx, graphics);
+      state.draw_coordinates(ctx, graphics, &mut glyphs);
+      state.draw_cursor(ctx, graphics);
+      state.draw_animation(ctx, graphics);
+      state.draw_annotations(ctx, graphics);
+      state.draw_book_moves(ctx, graphics);
+      state.draw_clock(ctx, graphics, &mut glyphs);
+      state.draw_move_input(ctx, graphics, &mut glyphs);
+      state.draw_setup(ctx, graphics, &mut glyphs);
       state.draw_selected_piece(ctx, graphics);
+
+      glyphs.factory.encoder.flush(device);
     });
   }
 
-  println!("{}", state.board.to_fen());
+  save_game(&options, &state.game());
+  log::info!(target: "rules", "final position {}", state.board.to_fen());
 }
\ No newline at end of file
@@ -99,7 +99,7 @@ fn generate_pawn_moves(board: &mut Board, start: usize, piece: Piece, file: usiz
     }
   }
 
-  if start_file == file && board.board[two_forward].piece.is_none() {
+  if start_file == file && board.board[one_forward].piece.is_none() && board.board[two_forward].piece.is_none() {
     board.moves.push(new_move(start, two_forward));
   }
 
@@ -129,6 +129,9 @@ fn generate_pawn_moves(board: &mut Board, start: usize, piece: Piece, file: usiz
       if piece.piece != PieceType::Pawn { return }
     }
 
+    /* only right after a two square push */
+    if last_move.start.abs_diff(last_move.target) != 2 { return }
+
     for i in 0 .. 2 {
       let other_pawn = (start as isize + EN_PASSANT_OFFSETS[i]) as usize;
       let target = (start as isize + direction_offset[i]) as usize;
@@ -169,27 +172,24 @@ pub fn generate_all_moves(board: &mut Board) {
 const fn compile_squares_to_edge() -> [[isize; 8]; 8 * 8] {
   let mut buf = [[0; 8]; 8 * 8];
 
+  /* rank and file steps matching DIRECTION_OFFSETS */
+  let rank_steps: [isize; 8] = [ 0, 0, -1, 1, -1, -1, 1, 1 ];
+  let file_steps: [isize; 8] = [ -1, 1, 0, 0, -1, 1, -1, 1 ];
+
   let mut i = 0;
   while i < 64 {
-    let row_index = i / 8;
-    let col_index = i % 8;
+    let rank = (i / 8) as isize;
+    let file = (i % 8) as isize;
 
     let mut j = 0;
     while j < 8 {
-      let direction = DIRECTION_OFFSETS[j];
       let mut distance = 0;
-      let mut cur_index = i as isize + direction;
-
-      while cur_index >= 0 && cur_index < 64 as isize && (cur_index as usize) % 8 != 7 && (cur_index as usize) % 8 != 0 {
-        let cur_row_index = cur_index as usize / 8;
-        let cur_col_index = cur_index as usize % 8;
+      let (mut cur_rank, mut cur_file) = (rank + rank_steps[j], file + file_steps[j]);
 
+      while cur_rank >= 0 && cur_rank < 8 && cur_file >= 0 && cur_file < 8 {
         distance += 1;
-        cur_index += direction;
-
-        if cur_row_index == row_index || cur_col_index == col_index {
-          break;
-        }
+        cur_rank += rank_steps[j];
+        cur_file += file_steps[j];
       }
 
       buf[i][j] = distance;
@@ -233,4 +233,36 @@ const fn compile_knight_moves() -> [[isize; 8]; 8 * 8] {
   }
 
   buf
-}
\ No newline at end of file
+}
+
+/* unlike generate_all_moves these leave the passed board alone */
+pub fn pseudo_legal_moves(board: &Board) -> Vec<Move> {
+  let mut board = board.clone();
+  generate_all_moves(&mut board);
+
+  board.moves
+}
+
+pub fn is_in_check(board: &Board, color: Color) -> bool {
+  let mut board = board.clone();
+  board.color_to_move = color;
+  board.color_to_move.reverse();
+
+  pseudo_legal_moves(&board).iter().any(|mov| {
+    match board.board[mov.target].piece {
+      Some(piece) => piece.piece == PieceType::King && piece.color == color,
+      None => false
+    }
+  })
+}
+
+pub fn legal_moves(board: &Board) -> Vec<Move> {
+  let color = board.color_to_move;
+
+  pseudo_legal_moves(board).into_iter().filter(|mov| {
+    let mut after = board.clone();
+    after.make_move(*mov);
+
+    !is_in_check(&after, color)
+  }).collect()
+}
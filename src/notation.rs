@@ -0,0 +1,147 @@
+use crate::board::{ Board, parse_square, square_name };
+use crate::move_generator::*;
+use crate::piece::PieceType;
+
+fn piece_type(c: char) -> Option<PieceType> {
+  match c {
+    'K' => Some(PieceType::King),
+    'Q' => Some(PieceType::Queen),
+    'R' => Some(PieceType::Rook),
+    'B' => Some(PieceType::Bishop),
+    'N' => Some(PieceType::Knight),
+    _ => None
+  }
+}
+
+/* e2e4 or e7e8q, pawns only promote to queens */
+pub fn parse_uci(board: &Board, text: &str) -> Option<Move> {
+  if !matches!(text.get(4 ..), Some("" | "q")) { return None; }
+
+  let start = parse_square(text.get(0 .. 2)?)?;
+  let target = parse_square(text.get(2 .. 4)?)?;
+
+  legal_moves(board).into_iter().find(|mov| mov.start == start && mov.target == target)
+}
+
+/* standard algebraic notation like e4, Nbd2, exd5 or e8=Q+, castling isn't a move here */
+pub fn parse_san(board: &Board, text: &str) -> Option<Move> {
+  /* san is all ascii, anything else would be sliced in the middle of a character below */
+  if !text.is_ascii() { return None; }
+
+  let text = text.trim_end_matches(['+', '#', '!', '?']);
+  /* moves otherwise always end on a square, so a trailing Q can only be a promotion */
+  let text = text.strip_suffix("=Q").or_else(|| text.strip_suffix('Q')).unwrap_or(text);
+
+  let mut chars = text.chars();
+  let piece = match text.chars().next().and_then(piece_type) {
+    Some(piece) => { chars.next(); piece }
+    None => PieceType::Pawn
+  };
+
+  let rest = chars.as_str().replace('x', "");
+  if rest.len() < 2 { return None; }
+
+  let (from, target) = rest.split_at(rest.len() - 2);
+  let target = parse_square(target)?;
+
+  /* whatever is left narrows down which piece moves, a file, a rank or both */
+  let mut file = None;
+  let mut rank = None;
+
+  for c in from.chars() {
+    match c {
+      'a' ..= 'h' => file = Some((c as u8 - b'a') as usize),
+      '1' ..= '8' => rank = Some((b'8' - c as u8) as usize),
+      _ => return None
+    }
+  }
+
+  let candidates = legal_moves(board).into_iter().filter(|mov| {
+    mov.target == target
+      && board.board[mov.start].piece.is_some_and(|p| p.piece == piece)
+      && file.is_none_or(|file| mov.start / 8 == file)
+      && rank.is_none_or(|rank| mov.start % 8 == rank)
+  }).collect::<Vec<_>>();
+
+  match candidates.as_slice() {
+    [mov] => Some(*mov),
+    _ => None
+  }
+}
+
+/* what the move box accepts, uci first since something like b2b4 isn't valid san anyway */
+pub fn parse_move(board: &Board, text: &str) -> Option<Move> {
+  let text = text.trim();
+
+  parse_uci(board, text).or_else(|| parse_san(board, text))
+}
+
+/* the san of a legal move, e.g. Nbd2, exd5 or e8=Q# */
+pub fn to_san(board: &Board, mov: Move) -> String {
+  let piece = board.board[mov.start].piece.unwrap();
+  let capture = board.board[mov.target].piece.is_some() || matches!(mov.special, SpecialMove::EnPassant(_));
+  let mut san = String::new();
+
+  if piece.piece == PieceType::Pawn {
+    if capture { san.push_str(&square_name(mov.start)[.. 1]); }
+  } else {
+    san.push(Into::<char>::into(piece.piece).to_ascii_uppercase());
+
+    /* the file if that tells the pieces apart, otherwise the rank, otherwise both */
+    let others = legal_moves(board).into_iter().filter(|other| {
+      other.target == mov.target && other.start != mov.start
+        && board.board[other.start].piece.is_some_and(|p| p.piece == piece.piece)
+    }).collect::<Vec<_>>();
+
+    if !others.is_empty() {
+      let name = square_name(mov.start);
+
+      if others.iter().all(|other| other.start / 8 != mov.start / 8) {
+        san.push_str(&name[.. 1]);
+      } else if others.iter().all(|other| other.start % 8 != mov.start % 8) {
+        san.push_str(&name[1 ..]);
+      } else {
+        san.push_str(&name);
+      }
+    }
+  }
+
+  if capture { san.push('x'); }
+  san.push_str(&square_name(mov.target));
+
+  if let SpecialMove::Promotion = mov.special { san.push_str("=Q"); }
+
+  let mut after = board.clone();
+  after.make_move(mov);
+
+  if is_in_check(&after, after.color_to_move) {
+    san.push(if legal_moves(&after).is_empty() { '#' } else { '+' });
+  }
+
+  san
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_moves() {
+    let board = Board::new();
+
+    assert_eq!(parse_move(&board, "e4").map(|mov| square_name(mov.target)), Some(String::from("e4")));
+    assert_eq!(parse_move(&board, "Nf3").map(|mov| square_name(mov.start)), Some(String::from("g1")));
+    assert_eq!(parse_move(&board, "g1f3").map(|mov| square_name(mov.target)), Some(String::from("f3")));
+    assert!(parse_move(&board, "e5").is_none());
+  }
+
+  #[test]
+  fn rejects_non_ascii_text() {
+    let board = Board::new();
+
+    for text in ["é4", "eé4", "Né3", "e4é", "ñ", "♘f3", "e2é4"] {
+      assert!(parse_move(&board, text).is_none(), "{} was accepted", text);
+      assert!(parse_san(&board, text).is_none(), "{} was accepted", text);
+    }
+  }
+}
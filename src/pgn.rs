@@ -0,0 +1,237 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::annotation::Annotations;
+use crate::board::{ Board, GameResult };
+use crate::fen;
+use crate::notation;
+use crate::piece::Color;
+
+/* a game as the position after every move, with the marks drawn on each of them */
+pub struct Game {
+  pub positions: Vec<Board>,
+  pub annotations: Vec<Annotations>,
+  pub result: Option<GameResult>
+}
+
+impl Game {
+  /* a game that hasn't had any moves yet */
+  pub fn new(board: Board) -> Self {
+    Game { positions: vec![board], annotations: vec![Annotations::default()], result: None }
+  }
+}
+
+fn result_tag(result: Option<GameResult>) -> &'static str {
+  match result {
+    Some(GameResult::Win(Color::White)) => "1-0",
+    Some(GameResult::Win(Color::Black)) => "0-1",
+    Some(GameResult::Draw) => "1/2-1/2",
+    None => "*"
+  }
+}
+
+fn invalid(message: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn write(game: &Game) -> String {
+  let result = result_tag(game.result);
+  let start = &game.positions[0];
+  let mut pgn = String::new();
+
+  for (name, value) in [("Event", "Casual game"), ("Site", "?"), ("Date", "????.??.??"), ("Round", "-"), ("White", "?"), ("Black", "?"), ("Result", result)] {
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+  }
+
+  if start.to_fen() != Board::new().to_fen() {
+    pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", start.to_fen()));
+  }
+
+  let mut tokens = Vec::new();
+  let comment = |i: usize| game.annotations.get(i).filter(|annotations| !annotations.is_empty()).map(|annotations| format!("{{{}}}", annotations.to_comment()));
+
+  tokens.extend(comment(0));
+
+  for i in 1 .. game.positions.len() {
+    let before = &game.positions[i - 1];
+    let number = before.ply / 2 + 1;
+
+    /* black's moves get their number again at the start or after a comment */
+    if before.color_to_move == Color::White {
+      tokens.push(format!("{}.", number));
+    } else if i == 1 || comment(i - 1).is_some() {
+      tokens.push(format!("{}...", number));
+    }
+
+    if let Some(mov) = game.positions[i].last_move {
+      tokens.push(notation::to_san(before, mov));
+    }
+
+    tokens.extend(comment(i));
+  }
+
+  tokens.push(result.to_string());
+
+  /* movetext lines are kept under 80 characters */
+  let mut line = String::new();
+
+  for token in tokens {
+    if !line.is_empty() && line.len() + token.len() + 1 > 79 {
+      pgn.push_str(&format!("\n{}", line));
+      line.clear();
+    }
+
+    if !line.is_empty() { line.push(' '); }
+    line.push_str(&token);
+  }
+
+  pgn.push_str(&format!("\n{}\n", line));
+  pgn
+}
+
+/* the first game in the text, variations are skipped */
+pub fn read(text: &str) -> io::Result<Game> {
+  let mut board = Board::new();
+  let mut movetext = String::new();
+
+  for line in text.lines().map(str::trim) {
+    /* a comment wrapped onto a new line can start with one of its [%cal ...] commands */
+    let in_comment = movetext.matches('{').count() > movetext.matches('}').count();
+
+    if line.starts_with('[') && !in_comment {
+      if !movetext.trim().is_empty() { break; }
+
+      /* [Name "value"] */
+      if let Some((name, value)) = line.trim_matches(['[', ']']).split_once(' ') {
+        if name == "FEN" {
+          board = fen::parse(value.trim().trim_matches('"')).map_err(|err| invalid(format!("invalid fen: {}", err)))?;
+        }
+      }
+    } else {
+      movetext.push_str(line);
+      movetext.push('\n');
+    }
+  }
+
+  let mut game = Game { positions: vec![board.clone()], annotations: vec![Annotations::default()], result: None };
+  let mut chars = movetext.chars().peekable();
+  let mut depth = 0;
+
+  while let Some(c) = chars.next() {
+    match c {
+      '{' => {
+        let comment = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+
+        if depth == 0 {
+          game.annotations.last_mut().unwrap().parse_comment(&comment);
+        }
+      }
+
+      ';' => chars.by_ref().take_while(|c| *c != '\n').for_each(drop),
+      '(' => depth += 1,
+      ')' => depth = depth.max(1) - 1,
+
+      c if c.is_whitespace() => {}
+
+      _ => {
+        let mut token = String::from(c);
+
+        while let Some(&c) = chars.peek() {
+          if c.is_whitespace() || "{}();".contains(c) { break; }
+
+          token.push(c);
+          chars.next();
+        }
+
+        if depth > 0 || token.starts_with('$') { continue; }
+
+        match token.as_str() {
+          "1-0" => game.result = Some(GameResult::Win(Color::White)),
+          "0-1" => game.result = Some(GameResult::Win(Color::Black)),
+          "1/2-1/2" => game.result = Some(GameResult::Draw),
+          "*" => {}
+
+          _ => {
+            /* move numbers can be stuck to the move like 12.e4 */
+            let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            if san.is_empty() { continue; }
+
+            let mov = notation::parse_san(&board, san)
+              .ok_or_else(|| invalid(format!("illegal move {} after {} plies", san, game.positions.len() - 1)))?;
+
+            board.make_move(mov);
+            game.positions.push(board.clone());
+            game.annotations.push(Annotations::default());
+          }
+        }
+      }
+    }
+  }
+
+  Ok(game)
+}
+
+pub fn save(game: &Game, path: &Path) -> io::Result<()> {
+  fs::write(path, write(game))
+}
+
+pub fn load(path: &Path) -> io::Result<Game> {
+  read(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::annotation::MarkColor;
+  use crate::board::parse_square;
+
+  fn square(name: &str) -> usize {
+    parse_square(name).unwrap()
+  }
+
+  fn comments(game: &Game) -> Vec<String> {
+    game.annotations.iter().map(Annotations::to_comment).collect()
+  }
+
+  #[test]
+  fn round_trips_arrows_and_marks() {
+    let mut game = Game::new(Board::new());
+
+    for san in ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"] {
+      let mut board = game.positions.last().unwrap().clone();
+      board.make_move(notation::parse_san(&board, san).unwrap());
+
+      game.positions.push(board);
+      game.annotations.push(Annotations::default());
+    }
+
+    game.annotations[0].toggle_square(MarkColor::Red, square("e4"));
+    game.annotations[2].toggle_arrow(MarkColor::Green, square("g1"), square("f3"));
+    game.annotations[2].toggle_arrow(MarkColor::Blue, square("f1"), square("b5"));
+
+    /* enough marks on one position that the comment gets a line of its own */
+    for name in ["a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3"] {
+      game.annotations[5].toggle_square(MarkColor::Yellow, square(name));
+    }
+
+    game.annotations[6].toggle_arrow(MarkColor::Red, square("b5"), square("c6"));
+    game.result = Some(GameResult::Draw);
+
+    let read = read(&write(&game)).unwrap();
+    let fens = |game: &Game| game.positions.iter().map(Board::to_fen).collect::<Vec<_>>();
+
+    assert_eq!(fens(&read), fens(&game));
+    assert_eq!(comments(&read), comments(&game));
+    assert_eq!(read.result, game.result);
+  }
+
+  #[test]
+  fn reads_comments_wrapped_onto_a_new_line() {
+    let pgn = "[Event \"?\"]\n\n1. e4 {\n[%cal Ge7e5]\n[%csl Rd5]} e5 2. Nf3 *\n";
+    let game = read(pgn).unwrap();
+
+    assert_eq!(game.positions.len(), 4);
+    assert_eq!(comments(&game)[1], "[%csl Rd5][%cal Ge7e5]");
+  }
+}
@@ -115,4 +115,22 @@ impl Piece {
       c
     }
   }
+
+  /* outlined for white and filled for black, as on a printed diagram */
+  pub fn to_unicode(self) -> char {
+    match (self.color, self.piece) {
+      (Color::White, PieceType::King) => '♔',
+      (Color::White, PieceType::Queen) => '♕',
+      (Color::White, PieceType::Rook) => '♖',
+      (Color::White, PieceType::Bishop) => '♗',
+      (Color::White, PieceType::Knight) => '♘',
+      (Color::White, PieceType::Pawn) => '♙',
+      (Color::Black, PieceType::King) => '♚',
+      (Color::Black, PieceType::Queen) => '♛',
+      (Color::Black, PieceType::Rook) => '♜',
+      (Color::Black, PieceType::Bishop) => '♝',
+      (Color::Black, PieceType::Knight) => '♞',
+      (Color::Black, PieceType::Pawn) => '♟'
+    }
+  }
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+use std::io::{ self, Cursor, Write };
+use std::path::Path;
+use std::time::Duration;
+
+use image::{ imageops, Delay, Frame, ImageFormat, Rgba, RgbaImage };
+use image::codecs::gif::{ GifEncoder, Repeat };
+use rusttype::{ point, Font, Scale };
+
+use crate::annotation::{ self, Annotations };
+use crate::assets::FONT;
+use crate::board::Board;
+use crate::fen;
+use crate::layout::Layout;
+use crate::pgn::Game;
+use crate::sprite;
+use crate::theme::{ PieceSet, Theme };
+
+/* everything drawn on a diagram besides the position */
+#[derive(Debug, Clone, Default)]
+pub struct Diagram {
+  pub flipped: bool,
+  pub coordinates: bool,
+  pub last_move: Option<(usize, usize)>,
+  pub annotations: Annotations
+}
+
+/* how a game is played back in a gif */
+#[derive(Debug, Clone)]
+pub struct GifOptions {
+  /* how long every position stays up */
+  pub delay: Duration,
+  /* added to the last position so the end can be seen before it loops */
+  pub final_pause: Duration,
+  pub flipped: bool,
+  pub coordinates: bool,
+  pub highlights: bool
+}
+
+impl Default for GifOptions {
+  fn default() -> Self {
+    GifOptions {
+      delay: Duration::from_secs(1),
+      final_pause: Duration::from_secs(3),
+      flipped: false,
+      coordinates: false,
+      highlights: true
+    }
+  }
+}
+
+/* draws boards into images without a window, the same way the gui does */
+pub struct Renderer {
+  pub theme: Theme,
+  pub square: u32,
+  sprites: Vec<RgbaImage>,
+  font: Font<'static>
+}
+
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: [f32; 4], coverage: f32) {
+  if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 { return; }
+
+  let pixel = image.get_pixel_mut(x as u32, y as u32);
+  let alpha = color[3] * coverage;
+
+  for i in 0 .. 3 {
+    pixel[i] = (pixel[i] as f32 * (1.0 - alpha) + color[i] * 255.0 * alpha).round() as u8;
+  }
+}
+
+fn fill_rect(image: &mut RgbaImage, (x, y): (f64, f64), size: f64, color: [f32; 4]) {
+  for py in y as i64 .. (y + size) as i64 {
+    for px in x as i64 .. (x + size) as i64 {
+      blend(image, px, py, color, 1.0);
+    }
+  }
+}
+
+/* convex polygons only, edges are smoothed by sampling every pixel 4 x 4 times */
+fn fill_polygon(image: &mut RgbaImage, points: &[[f64; 2]], color: [f32; 4]) {
+  const SAMPLES: usize = 4;
+
+  let inside = |x: f64, y: f64| {
+    let sides = (0 .. points.len()).map(|i| {
+      let ([ax, ay], [bx, by]) = (points[i], points[(i + 1) % points.len()]);
+
+      (bx - ax) * (y - ay) - (by - ay) * (x - ax)
+    });
+
+    let (mut left, mut right) = (false, false);
+
+    for side in sides {
+      left |= side < 0.0;
+      right |= side > 0.0;
+    }
+
+    !(left && right)
+  };
+
+  let min_x = points.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min).floor() as i64;
+  let max_x = points.iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max).ceil() as i64;
+  let min_y = points.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min).floor() as i64;
+  let max_y = points.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max).ceil() as i64;
+
+  for py in min_y .. max_y {
+    for px in min_x .. max_x {
+      let mut hits = 0;
+
+      for sy in 0 .. SAMPLES {
+        for sx in 0 .. SAMPLES {
+          let (x, y) = (px as f64 + (sx as f64 + 0.5) / SAMPLES as f64, py as f64 + (sy as f64 + 0.5) / SAMPLES as f64);
+
+          if inside(x, y) { hits += 1; }
+        }
+      }
+
+      if hits > 0 {
+        blend(image, px, py, color, hits as f32 / (SAMPLES * SAMPLES) as f32);
+      }
+    }
+  }
+}
+
+impl Renderer {
+  /* square is the size of a square in pixels */
+  pub fn new(theme: Theme, piece_set: &PieceSet, square: u32) -> Result<Self, String> {
+    let sheet = sprite::open_sheet(piece_set)?;
+    let sprites = sprite::sprite_images(&sheet, piece_set, square)?.into_iter().map(|image| image.into_rgba8()).collect();
+    log::debug!(target: "rendering", "pieces drawn at {} pixels", square);
+
+    Ok(Renderer {
+      theme,
+      square,
+      sprites,
+      font: Font::try_from_bytes(FONT).unwrap()
+    })
+  }
+
+  fn draw_text(&self, image: &mut RgbaImage, text: &str, size: f32, [x, y]: [f64; 2], color: [f32; 4]) {
+    for glyph in self.font.layout(text, Scale::uniform(size), point(x as f32, y as f32)) {
+      if let Some(bounds) = glyph.pixel_bounding_box() {
+        glyph.draw(|gx, gy, coverage| {
+          blend(image, (bounds.min.x + gx as i32) as i64, (bounds.min.y + gy as i32) as i64, color, coverage);
+        });
+      }
+    }
+  }
+
+  pub fn render(&self, board: &Board, diagram: &Diagram) -> RgbaImage {
+    let layout = Layout { square: self.square as f64, board_x: 0.0, board_y: 0.0, flipped: diagram.flipped };
+    let mut image = RgbaImage::from_pixel(self.square * 8, self.square * 8, Rgba([0, 0, 0, 255]));
+
+    for idx in 0 .. 64 {
+      let is_light_square = (idx / 8 + idx % 8) % 2 == 0;
+      let color = match diagram.last_move {
+        Some((from, to)) if idx == from || idx == to => self.theme.last_move,
+        _ if is_light_square => self.theme.light_square,
+        _ => self.theme.dark_square
+      };
+
+      let (x, y) = layout.square_pos(idx);
+      fill_rect(&mut image, (x, y), layout.square, color);
+
+      if let Some(piece) = board.get_piece_at(idx) {
+        imageops::overlay(&mut image, &self.sprites[piece.sprite_sheet_pos()], x as i64, y as i64);
+      }
+    }
+
+    if diagram.coordinates {
+      let size = layout.font_size(13.0);
+
+      for (idx, label, position) in layout.coordinate_labels(size as f64) {
+        let color = if (idx / 8 + idx % 8) % 2 == 0 { self.theme.dark_square } else { self.theme.light_square };
+
+        self.draw_text(&mut image, &label, size as f32, position, color);
+      }
+    }
+
+    for (mark, idx) in &diagram.annotations.squares {
+      let mut fill = self.theme.annotation(*mark);
+      fill[3] *= 0.6;
+
+      fill_rect(&mut image, layout.square_pos(*idx), layout.square, fill);
+    }
+
+    for (mark, from, to) in &diagram.annotations.arrows {
+      let (shaft, head) = annotation::arrow_polygons(layout.square_center(*from), layout.square_center(*to), layout.square);
+
+      fill_polygon(&mut image, &shaft, self.theme.annotation(*mark));
+      fill_polygon(&mut image, &head, self.theme.annotation(*mark));
+    }
+
+    image
+  }
+}
+
+/* a diagram of the position in fen, for docs and chat bots, Theme::default() and
+   PieceSet::default() are what the gui starts with and square is in pixels */
+pub fn render(fen: &str, diagram: &Diagram, theme: Theme, piece_set: &PieceSet, square: u32) -> Result<RgbaImage, String> {
+  let board = fen::parse(fen).map_err(|err| format!("invalid fen: {}", err))?;
+  let renderer = Renderer::new(theme, piece_set, square).map_err(|err| format!("couldn't open piece sheet: {}", err))?;
+
+  Ok(renderer.render(&board, diagram))
+}
+
+/* a path of - writes to stdout, for piping into other tools */
+fn write_output(bytes: &[u8], path: &Path) -> io::Result<()> {
+  if path == Path::new("-") {
+    io::stdout().write_all(bytes)
+  } else {
+    std::fs::write(path, bytes)
+  }
+}
+
+pub fn save_png(image: &RgbaImage, path: &Path) -> io::Result<()> {
+  let mut png = Cursor::new(Vec::new());
+
+  image.write_to(&mut png, ImageFormat::Png).map_err(io::Error::other)?;
+  write_output(png.get_ref(), path)?;
+
+  log::info!(target: "rendering", "wrote a {} x {} png to {}", image.width(), image.height(), path.display());
+  Ok(())
+}
+
+/* every position of the game one after the other, with the game's arrows and marks, looping forever */
+pub fn save_gif(renderer: &Renderer, game: &Game, options: &GifOptions, path: &Path) -> io::Result<()> {
+  let mut gif = Vec::new();
+
+  {
+    /* speed trades palette quality for time, 10 is what the encoder suggests */
+    let mut encoder = GifEncoder::new_with_speed(&mut gif, 10);
+    encoder.set_repeat(Repeat::Infinite).map_err(io::Error::other)?;
+
+    for (i, board) in game.positions.iter().enumerate() {
+      let diagram = Diagram {
+        flipped: options.flipped,
+        coordinates: options.coordinates,
+        last_move: board.last_move.filter(|_| options.highlights).map(|mov| (mov.start, mov.target)),
+        annotations: game.annotations.get(i).cloned().unwrap_or_default()
+      };
+
+      let delay = if i + 1 == game.positions.len() { options.delay + options.final_pause } else { options.delay };
+      let frame = Frame::from_parts(renderer.render(board, &diagram), 0, 0, Delay::from_saturating_duration(delay));
+
+      encoder.encode_frame(frame).map_err(io::Error::other)?;
+      log::trace!(target: "rendering", "frame {} of {}", i + 1, game.positions.len());
+    }
+  }
+
+  write_output(&gif, path)?;
+
+  log::info!(target: "rendering", "wrote {} frames to {}", game.positions.len(), path.display());
+  Ok(())
+}
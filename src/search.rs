@@ -0,0 +1,359 @@
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread::{ self, JoinHandle };
+
+use crate::board::Board;
+use crate::move_generator::*;
+use crate::piece::{ Color, PieceType };
+use crate::tablebase::{ Tablebase, Wdl };
+use crate::transposition::{ Bound, Entry, TranspositionTable };
+use crate::zobrist;
+
+const MATE: i32 = 30000;
+const INFINITY: i32 = 32000;
+const MAX_DEPTH: u8 = 64;
+/* below the mate scores so mates found by the search still win out */
+const TABLEBASE_WIN: i32 = MATE - 2000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+  /* Threads, with 1 no helpers are spawned and the same position and table always give the same move */
+  pub threads: usize,
+  pub depth: u8
+}
+
+impl Default for SearchOptions {
+  fn default() -> Self {
+    SearchOptions {
+      threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+      depth: 5
+    }
+  }
+}
+
+/* a running lazy smp search, the main thread decides the move and helpers only fill the table */
+pub struct Search {
+  stop: Arc<AtomicBool>,
+  main: JoinHandle<Option<Move>>,
+  helpers: Vec<JoinHandle<()>>
+}
+
+struct Worker<'a> {
+  id: usize,
+  tt: &'a TranspositionTable,
+  tablebase: Option<&'a Tablebase>,
+  stop: &'a AtomicBool
+}
+
+impl Search {
+  pub fn start(board: &Board, options: SearchOptions, tt: Arc<TranspositionTable>, tablebase: Option<Arc<Tablebase>>) -> Self {
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let helpers = (1 .. options.threads.max(1)).map(|id| {
+      let (board, tt, tablebase, stop) = (board.clone(), tt.clone(), tablebase.clone(), stop.clone());
+
+      thread::spawn(move || {
+        Worker::new(id, &tt, tablebase.as_deref(), &stop).iterate(&board, MAX_DEPTH);
+      })
+    }).collect();
+
+    let main = {
+      let (board, tt, tablebase, stop) = (board.clone(), tt.clone(), tablebase.clone(), stop.clone());
+
+      thread::spawn(move || {
+        let best = Worker::new(0, &tt, tablebase.as_deref(), &stop).iterate(&board, options.depth);
+
+        /* helpers have nothing left to help with */
+        stop.store(true, Ordering::Relaxed);
+
+        best
+      })
+    };
+
+    Search { stop, main, helpers }
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.main.is_finished()
+  }
+
+  /* aborts the search and returns the best move of the last finished iteration */
+  pub fn stop(self) -> Option<Move> {
+    self.stop.store(true, Ordering::Relaxed);
+    self.join()
+  }
+
+  pub fn wait(self) -> Option<Move> {
+    self.join()
+  }
+
+  fn join(self) -> Option<Move> {
+    let best = self.main.join().unwrap_or(None);
+
+    for helper in self.helpers {
+      let _ = helper.join();
+    }
+
+    best
+  }
+}
+
+fn piece_value(piece: PieceType) -> i32 {
+  match piece {
+    PieceType::Pawn => 100,
+    PieceType::Knight => 320,
+    PieceType::Bishop => 330,
+    PieceType::Rook => 500,
+    PieceType::Queen => 900,
+    PieceType::King => 0
+  }
+}
+
+/* material plus a small bonus for central pieces and advanced pawns, from the side to move's view */
+pub fn evaluate(board: &Board) -> i32 {
+  let mut score = 0;
+
+  for (idx, tile) in board.board.iter().enumerate() {
+    if let Some(piece) = tile.piece {
+      let (rank, file) = ((idx / 8) as i32, (idx % 8) as i32);
+      let center = 14 - ((2 * rank - 7).abs() + (2 * file - 7).abs());
+
+      let bonus = match piece.piece {
+        PieceType::Pawn => if piece.color == Color::White { (6 - file) * 5 } else { (file - 1) * 5 },
+        PieceType::Knight | PieceType::Bishop => center * 3,
+        PieceType::Queen => center,
+        _ => 0
+      };
+
+      let value = piece_value(piece.piece) + bonus;
+
+      score += if piece.color == board.color_to_move { value } else { -value };
+    }
+  }
+
+  score
+}
+
+fn captures_king(board: &Board, moves: &[Move]) -> bool {
+  moves.iter().any(|mov| {
+    match board.board[mov.target].piece {
+      Some(piece) => piece.piece == PieceType::King,
+      None => false
+    }
+  })
+}
+
+/* mate scores are stored relative to the node so they stay valid at any ply */
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+  if score > MATE - 1000 { score + ply } else if score < -MATE + 1000 { score - ply } else { score }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+  if score > MATE - 1000 { score - ply } else if score < -MATE + 1000 { score + ply } else { score }
+}
+
+impl <'a>Worker<'a> {
+  fn new(id: usize, tt: &'a TranspositionTable, tablebase: Option<&'a Tablebase>, stop: &'a AtomicBool) -> Self {
+    Worker { id, tt, tablebase, stop }
+  }
+
+  fn stopped(&self) -> bool {
+    self.stop.load(Ordering::Relaxed)
+  }
+
+  fn iterate(&mut self, board: &Board, max_depth: u8) -> Option<Move> {
+    /* with the position in the tablebase there is nothing left to search */
+    if let Some(mov) = self.tablebase.and_then(|tablebase| tablebase.best_move(board)) {
+      return Some(mov);
+    }
+
+    let mut best = legal_moves(board).first().copied();
+
+    for depth in 1 ..= max_depth {
+      /* helpers skip ahead on odd ids so the threads don't all search the same tree */
+      let depth = if self.id % 2 == 1 { (depth + 1).min(MAX_DEPTH) } else { depth };
+
+      match self.root(board, depth) {
+        Some(mov) if !self.stop.load(Ordering::Relaxed) => best = Some(mov),
+        _ => break
+      }
+    }
+
+    best
+  }
+
+  fn order_moves(&self, board: &Board, moves: &mut [Move], tt_move: Option<(usize, usize)>) {
+    moves.sort_by_cached_key(|mov| {
+      if tt_move == Some((mov.start, mov.target)) {
+        return -INFINITY;
+      }
+
+      match (board.board[mov.target].piece, board.board[mov.start].piece) {
+        (Some(victim), Some(attacker)) => piece_value(attacker.piece) / 10 - piece_value(victim.piece),
+        _ => 0
+      }
+    });
+
+    /* helpers try quiet moves in a different order */
+    if self.id > 0 {
+      let quiet = moves.iter().position(|mov| board.board[mov.target].piece.is_none()).unwrap_or(moves.len());
+      let len = moves.len() - quiet;
+
+      if len > 1 {
+        moves[quiet ..].rotate_left(self.id % len);
+      }
+    }
+  }
+
+  fn root(&mut self, board: &Board, depth: u8) -> Option<Move> {
+    let key = zobrist::hash(board);
+    let mut moves = legal_moves(board);
+    let mut alpha = -INFINITY;
+    let mut best = None;
+
+    self.order_moves(board, &mut moves, self.tt.probe(key).and_then(|entry| entry.best));
+
+    for mov in moves {
+      let mut child = board.clone();
+      child.make_move(mov);
+
+      let score = -self.negamax(&child, depth - 1, 1, -INFINITY, -alpha);
+
+      if self.stop.load(Ordering::Relaxed) { return None; }
+
+      if score > alpha {
+        alpha = score;
+        best = Some(mov);
+      }
+    }
+
+    if let Some(mov) = best {
+      self.tt.store(key, Entry { score: alpha, depth, bound: Bound::Exact, best: Some((mov.start, mov.target)) });
+    }
+
+    best
+  }
+
+  fn negamax(&mut self, board: &Board, depth: u8, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+    if self.stopped() { return 0; }
+
+    let mut moves = pseudo_legal_moves(board);
+
+    /* the last move left the king hanging */
+    if captures_king(board, &moves) { return MATE; }
+
+    if let Some(wdl) = self.tablebase.and_then(|tablebase| tablebase.probe_wdl(board)) {
+      return match wdl {
+        Wdl::Win => TABLEBASE_WIN,
+        Wdl::Loss => -TABLEBASE_WIN,
+        _ => 0
+      };
+    }
+
+    if depth == 0 { return self.quiesce(board, alpha, beta); }
+
+    let key = zobrist::hash(board);
+    let entry = self.tt.probe(key);
+
+    if let Some(entry) = entry {
+      if entry.depth >= depth {
+        let score = score_from_tt(entry.score, ply);
+
+        match entry.bound {
+          Bound::Exact => return score,
+          Bound::Lower if score >= beta => return score,
+          Bound::Upper if score <= alpha => return score,
+          _ => {}
+        }
+      }
+    }
+
+    self.order_moves(board, &mut moves, entry.and_then(|entry| entry.best));
+
+    let original_alpha = alpha;
+    let mut best_score = -INFINITY;
+    let mut best_move = None;
+
+    for mov in moves {
+      let mut child = board.clone();
+      child.make_move(mov);
+
+      let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha);
+
+      /* illegal, our king could be taken */
+      if score == -MATE { continue; }
+
+      if score > best_score {
+        best_score = score;
+        best_move = Some((mov.start, mov.target));
+      }
+
+      if score > alpha { alpha = score; }
+      if alpha >= beta { break; }
+    }
+
+    if best_move.is_none() {
+      return if is_in_check(board, board.color_to_move) { -MATE + ply } else { 0 };
+    }
+
+    let bound = if best_score >= beta {
+      Bound::Lower
+    } else if best_score > original_alpha {
+      Bound::Exact
+    } else {
+      Bound::Upper
+    };
+
+    self.tt.store(key, Entry { score: score_to_tt(best_score, ply), depth, bound, best: best_move });
+
+    best_score
+  }
+
+  fn quiesce(&mut self, board: &Board, mut alpha: i32, beta: i32) -> i32 {
+    if self.stopped() { return 0; }
+
+    let stand_pat = evaluate(board);
+
+    if stand_pat >= beta { return stand_pat; }
+    if stand_pat > alpha { alpha = stand_pat; }
+
+    let mut moves: Vec<Move> = pseudo_legal_moves(board).into_iter()
+      .filter(|mov| board.board[mov.target].piece.is_some())
+      .collect();
+
+    if captures_king(board, &moves) { return MATE; }
+
+    self.order_moves(board, &mut moves, None);
+
+    for mov in moves {
+      let mut child = board.clone();
+      child.make_move(mov);
+
+      let score = -self.quiesce(&child, -beta, -alpha);
+
+      if score == -MATE { continue; }
+      if score >= beta { return score; }
+      if score > alpha { alpha = score; }
+    }
+
+    alpha
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn one_thread_always_picks_the_same_move() {
+    let board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+    let options = SearchOptions { threads: 1, depth: 3 };
+
+    let moves = (0 .. 3).map(|_| {
+      let mov = Search::start(&board, options, Arc::new(TranspositionTable::new(1)), None).wait().unwrap();
+      (mov.start, mov.target)
+    }).collect::<Vec<_>>();
+
+    assert!(moves.iter().all(|mov| *mov == moves[0]), "{:?}", moves);
+  }
+}
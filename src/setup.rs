@@ -0,0 +1,136 @@
+use crate::board::{ Board, square_name };
+use crate::fen;
+use crate::layout::Layout;
+use crate::piece::{ Color, Piece, PieceType };
+
+/* the palette in the panel, three to a row with white on top */
+const PALETTE: &str = "KQRBNPkqrbnp";
+
+/* a position being put together by hand */
+pub struct Setup {
+  /* only the pieces and the side to move are used */
+  pub board: Board,
+  /* K Q k q */
+  pub castling: [bool; 4],
+  pub en_passant: Option<usize>,
+  /* put down by clicking a square */
+  pub brush: Option<Piece>,
+  /* why the position couldn't be played */
+  pub error: Option<String>
+}
+
+pub fn same_piece(a: Piece, b: Piece) -> bool {
+  a.color == b.color && a.piece == b.piece
+}
+
+impl Setup {
+  pub fn new(board: &Board) -> Self {
+    Setup {
+      board: board.clone(),
+      castling: board.castling_rights(),
+      en_passant: board.en_passant_square(),
+      brush: None,
+      error: None
+    }
+  }
+
+  pub fn piece_at(&self, idx: usize) -> Option<Piece> {
+    self.board.board[idx].piece
+  }
+
+  pub fn set(&mut self, idx: usize, piece: Option<Piece>) {
+    self.board.board[idx].piece = piece.map(|piece| Piece { moved: false, ..piece });
+    self.changed();
+  }
+
+  pub fn clear(&mut self) {
+    for idx in 0 .. 8 * 8 {
+      self.board.board[idx].piece = None;
+    }
+
+    self.castling = [false; 4];
+    self.changed();
+  }
+
+  pub fn set_color_to_move(&mut self, color: Color) {
+    self.board.color_to_move = color;
+    self.changed();
+  }
+
+  pub fn toggle_castling(&mut self, right: usize) {
+    self.castling[right] = !self.castling[right];
+    self.error = None;
+  }
+
+  /* squares a pawn of the side that just moved can have skipped over */
+  pub fn en_passant_squares(&self) -> Vec<usize> {
+    /* rows count down from the 8th rank, so black pawns push towards higher rows */
+    let (color, row, step) = if self.board.color_to_move == Color::White { (Color::Black, 3, -1) } else { (Color::White, 4, 1) };
+
+    (0 .. 8).map(|column| column * 8 + row).filter(|idx| {
+      let skipped = (*idx as isize + step) as usize;
+      let start = (*idx as isize + step * 2) as usize;
+
+      self.piece_at(*idx).is_some_and(|piece| piece.piece == PieceType::Pawn && piece.color == color)
+        && self.piece_at(skipped).is_none() && self.piece_at(start).is_none()
+    }).map(|idx| (idx as isize + step) as usize).collect()
+  }
+
+  /* none, then every possible square in turn */
+  pub fn next_en_passant(&mut self) {
+    let squares = self.en_passant_squares();
+
+    self.en_passant = match self.en_passant.and_then(|square| squares.iter().position(|s| *s == square)) {
+      Some(i) => squares.get(i + 1).copied(),
+      None => squares.first().copied()
+    };
+
+    self.error = None;
+  }
+
+  /* an en passant square the position can't have anymore is dropped */
+  fn changed(&mut self) {
+    if self.en_passant.is_some_and(|square| !self.en_passant_squares().contains(&square)) {
+      self.en_passant = None;
+    }
+
+    self.error = None;
+  }
+
+  pub fn to_fen(&self) -> String {
+    let placement = self.board.to_fen().split(' ').next().unwrap_or("").to_string();
+
+    let castling = "KQkq".chars().zip(self.castling).filter(|(_, right)| *right).map(|(c, _)| c).collect::<String>();
+    let castling = if castling.is_empty() { String::from("-") } else { castling };
+
+    format!("{} {} {} {} 0 1",
+      placement,
+      if self.board.color_to_move == Color::White { "w" } else { "b" },
+      castling,
+      self.en_passant.map(square_name).unwrap_or_else(|| String::from("-"))
+    )
+  }
+
+  /* the board to play from, or what's wrong with the position */
+  pub fn finish(&self) -> Result<Board, String> {
+    fen::parse(&self.to_fen())
+  }
+}
+
+/* every palette piece with the x, y and size of its cell */
+pub fn palette(layout: &Layout) -> Vec<(Piece, [f64; 3])> {
+  let [x, y, width, _] = layout.panel();
+  let size = (width - layout.square / 2.0) / 3.0;
+
+  PALETTE.chars().enumerate().map(|(i, c)| {
+    let cell = [x + layout.square / 4.0 + (i % 3) as f64 * size, y + layout.square / 4.0 + (i / 3) as f64 * size, size];
+
+    (Piece::from_fen(c).unwrap(), cell)
+  }).collect()
+}
+
+pub fn palette_at(layout: &Layout, position: [f64; 2]) -> Option<Piece> {
+  palette(layout).into_iter().find(|(_, [x, y, size])| {
+    (*x .. x + size).contains(&position[0]) && (*y .. y + size).contains(&position[1])
+  }).map(|(piece, _)| piece)
+}
@@ -1,44 +1,129 @@
-use image::{ imageops::FilterType, DynamicImage };
+use std::fs;
+use std::path::Path;
+
+use image::{ imageops::FilterType, DynamicImage, RgbaImage };
 use piston_window::{ Texture, TextureSettings, G2dTextureContext, G2dTexture };
+use resvg::{ tiny_skia, usvg };
+
+use crate::theme::PieceSet;
 
-use crate::{ PIECE_HEIGHT, PIECE_WIDTH };
+/* the default sheet lives in the binary so the game runs from any directory */
+const DEFAULT_SHEET: &[u8] = include_bytes!("../assets/pieces.png");
+
+pub enum Sheet {
+  Raster(DynamicImage),
+  /* vector pieces are rasterized again at every square size so they stay sharp */
+  Svg(Box<usvg::Tree>),
+  Files(Vec<usvg::Tree>)
+}
 
 pub struct SpriteHandler<'a> {
   pub sprites: Vec<G2dTexture>,
+  pub piece_set: PieceSet,
   ctx: &'a mut G2dTextureContext,
-  sheet: DynamicImage,
+  sheet: Sheet,
 }
 
-impl <'a>SpriteHandler<'a> {
-  pub fn new(sheet: &str, ctx: &'a mut G2dTextureContext) -> Self {
+fn open_svg(path: &Path) -> Result<usvg::Tree, String> {
+  let data = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
 
+  usvg::Tree::from_data(&data, &usvg::Options::default()).map_err(|err| format!("{}: {}", path.display(), err))
+}
 
-    return SpriteHandler {
-      sheet: image::open(sheet).unwrap(),
-      sprites: Vec::new(),
-      ctx
-    }
+pub fn open_sheet(piece_set: &PieceSet) -> Result<Sheet, String> {
+  if let Some(files) = piece_set.piece_files() {
+    return files.iter().map(|path| open_svg(path)).collect::<Result<_, _>>().map(Sheet::Files);
+  }
+
+  match &piece_set.path {
+    Some(path) if path.extension().is_some_and(|ext| ext == "svg") => open_svg(path).map(|tree| Sheet::Svg(Box::new(tree))),
+    Some(path) => image::open(path).map(Sheet::Raster).map_err(|err| format!("{}: {}", path.display(), err)),
+    None => image::load_from_memory(DEFAULT_SHEET).map(Sheet::Raster).map_err(|err| err.to_string())
   }
+}
+
+/* one cell of an svg split into a columns x rows grid, drawn at size x size pixels,
+   cells are drawn on their own so a big grid never needs a pixmap of the whole sheet */
+fn rasterize(tree: &usvg::Tree, size: u32, (columns, rows): (u32, u32), (x, y): (u32, u32)) -> Result<DynamicImage, String> {
+  let mut pixmap = tiny_skia::Pixmap::new(size.max(1), size.max(1))
+    .ok_or_else(|| format!("{} pixels is too big to draw a piece at", size))?;
+  let (cell, tree_size) = (pixmap.width() as f32, tree.size());
+
+  let transform = tiny_skia::Transform::from_scale(cell * columns as f32 / tree_size.width(), cell * rows as f32 / tree_size.height())
+    .post_translate(-cell * x as f32, -cell * y as f32);
+
+  resvg::render(tree, transform, &mut pixmap.as_mut());
+
+  /* tiny-skia keeps premultiplied alpha, textures want it straight */
+  let pixels = pixmap.pixels().iter().flat_map(|pixel| {
+    let color = pixel.demultiply();
+
+    [color.red(), color.green(), color.blue(), color.alpha()]
+  }).collect();
+
+  Ok(DynamicImage::ImageRgba8(RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels).unwrap()))
+}
 
-  pub fn load(&mut self) {
-    let (sprite_width, sprite_height) = (self.sheet.width() / 6, self.sheet.height() / 2);
-
-    for y in 0 .. 2 {
-      for x in 0 .. 6 {
-        let image = self.sheet.crop(
-          (x * sprite_width) as u32,
-          (y * sprite_height) as u32,
-          sprite_width as u32,
-          sprite_height as u32
-        ).resize(PIECE_WIDTH as u32, PIECE_HEIGHT as u32, FilterType::Lanczos3);
-
-        self.sprites.push(Texture::from_image(
-            self.ctx,
-            &image.into_rgba8(),
-            &TextureSettings::new()).unwrap()
-          );
+/* every piece at size x size pixels, in sprite order */
+pub fn sprite_images(sheet: &Sheet, piece_set: &PieceSet, size: u32) -> Result<Vec<DynamicImage>, String> {
+  match sheet {
+    Sheet::Files(trees) => trees.iter().map(|tree| rasterize(tree, size, (1, 1), (0, 0))).collect(),
+    Sheet::Svg(tree) => {
+      piece_set.cells().into_iter().map(|cell| rasterize(tree, size, (piece_set.columns, piece_set.rows), cell)).collect()
+    }
+    Sheet::Raster(sheet) => {
+      let sprite_width = sheet.width() / piece_set.columns;
+      let sprite_height = sheet.height() / piece_set.rows;
+
+      if sprite_width == 0 || sprite_height == 0 {
+        return Err(format!("a {} x {} sheet can't be split into {} x {} pieces", sheet.width(), sheet.height(), piece_set.columns, piece_set.rows));
       }
+
+      Ok(piece_set.cells().into_iter().map(|(x, y)| {
+        sheet.crop_imm(
+          x * sprite_width,
+          y * sprite_height,
+          sprite_width,
+          sprite_height
+        ).resize(size, size, FilterType::Lanczos3)
+      }).collect())
     }
+  }
+}
+
+fn textures(ctx: &mut G2dTextureContext, sheet: &Sheet, piece_set: &PieceSet, size: u32) -> Result<Vec<G2dTexture>, String> {
+  sprite_images(sheet, piece_set, size)?.into_iter().map(|image| {
+    Texture::from_image(ctx, &image.into_rgba8(), &TextureSettings::new()).map_err(|err| err.to_string())
+  }).collect()
+}
+
+impl <'a>SpriteHandler<'a> {
+  /* the sprites are drawn right away at size x size pixels */
+  pub fn new(piece_set: PieceSet, sheet: Sheet, ctx: &'a mut G2dTextureContext, size: u32) -> Result<Self, String> {
+    return Ok(SpriteHandler {
+      sprites: textures(ctx, &sheet, &piece_set, size)?,
+      sheet,
+      piece_set,
+      ctx
+    })
+  }
+
+  /* swaps the sheet, the old one stays if the new one can't be opened or drawn */
+  pub fn set_piece_set(&mut self, piece_set: PieceSet, size: u32) -> Result<(), String> {
+    let sheet = open_sheet(&piece_set)?;
 
+    self.sprites = textures(self.ctx, &sheet, &piece_set, size)?;
+    self.sheet = sheet;
+    self.piece_set = piece_set;
+
+    Ok(())
   }
-}
\ No newline at end of file
+
+  /* (re)rasterizes every sprite at size x size pixels, the old ones stay if that fails */
+  pub fn load(&mut self, size: u32) -> Result<(), String> {
+    self.sprites = textures(self.ctx, &self.sheet, &self.piece_set, size)?;
+
+    Ok(())
+  }
+}
+
@@ -0,0 +1,100 @@
+use std::io;
+use std::path::PathBuf;
+
+use shakmaty::{ CastlingMode, Chess };
+use shakmaty::fen::Fen;
+use shakmaty_syzygy::Tablebase as Tables;
+
+pub use shakmaty_syzygy::Wdl;
+
+use crate::assets::asset_path;
+use crate::board::Board;
+use crate::move_generator::*;
+
+#[derive(Debug, Clone)]
+pub struct TablebaseOptions {
+  /* directory with the .rtbw and .rtbz files */
+  pub path: PathBuf,
+  /* only positions with at most this many pieces are probed */
+  pub max_pieces: usize
+}
+
+pub struct Tablebase {
+  pub options: TablebaseOptions,
+  tables: Tables<Chess>
+}
+
+impl Default for TablebaseOptions {
+  fn default() -> Self {
+    TablebaseOptions {
+      path: asset_path("tablebases"),
+      max_pieces: 6
+    }
+  }
+}
+
+pub fn piece_count(board: &Board) -> usize {
+  board.board.iter().filter(|tile| tile.piece.is_some()).count()
+}
+
+/* syzygy squares count from a1 = 0 while ours are rank * 8 + file from the top left */
+fn from_square(square: usize) -> usize {
+  (square % 8) * 8 + 7 - square / 8
+}
+
+impl Tablebase {
+  pub fn open(options: TablebaseOptions) -> io::Result<Self> {
+    let mut tables = Tables::new();
+
+    if tables.add_directory(&options.path)? == 0 {
+      return Err(io::Error::new(io::ErrorKind::NotFound, "no tablebase files in directory"));
+    }
+
+    Ok(Tablebase { options, tables })
+  }
+
+  pub fn max_pieces(&self) -> usize {
+    self.options.max_pieces.min(self.tables.max_pieces())
+  }
+
+  fn position(&self, board: &Board) -> Option<Chess> {
+    if piece_count(board) > self.max_pieces() { return None; }
+
+    board.to_fen().parse::<Fen>().ok()?.into_position(CastlingMode::Standard).ok()
+  }
+
+  /* win, draw or loss for the side to move, as if the last move was a capture or pawn move */
+  pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+    self.tables.probe_wdl_after_zeroing(&self.position(board)?).ok()
+  }
+
+  /* plies to the next capture or pawn move that keeps the result, negative when losing */
+  pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+    let dtz = self.tables.probe_dtz(&self.position(board)?).ok()?;
+
+    Some(dtz.ignore_rounding().0)
+  }
+
+  pub fn best_move(&self, board: &Board) -> Option<Move> {
+    let (best, _) = self.tables.best_move(&self.position(board)?).ok()??;
+    let (start, target) = (from_square(usize::from(best.from()?)), from_square(usize::from(best.to())));
+
+    legal_moves(board).into_iter().find(|mov| mov.start == start && mov.target == target)
+  }
+
+  /* e.g. "Win (DTZ 12)" for the side to move */
+  pub fn describe(&self, board: &Board) -> Option<String> {
+    let wdl = match self.probe_wdl(board)? {
+      Wdl::Win => "Win",
+      Wdl::CursedWin => "Cursed win",
+      Wdl::Draw => "Draw",
+      Wdl::BlessedLoss => "Blessed loss",
+      Wdl::Loss => "Loss"
+    };
+
+    match self.probe_dtz(board) {
+      Some(dtz) => Some(format!("{} (DTZ {})", wdl, dtz)),
+      None => Some(wdl.to_string())
+    }
+  }
+}
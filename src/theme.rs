@@ -0,0 +1,194 @@
+use std::fs;
+use std::io;
+use std::path::{ Path, PathBuf };
+
+use serde::{ Deserialize, Deserializer };
+use serde::de::Error;
+
+use crate::annotation::MarkColor;
+
+macro_rules! rgb_to_color {
+    ($r:expr, $g:expr, $b:expr) => {
+      [$r as f32 / 255.0, $g as f32 / 255.0, $b as f32 / 255.0, 1.0]
+    };
+}
+
+/* every color the gui draws with, missing entries in a theme file keep the default */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+  #[serde(deserialize_with = "hex_color")] pub dark_square: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub light_square: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub selected_square: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub last_move: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub check: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub premove: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub hint: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub legal_dark_square: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub legal_light_square: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub legal_move: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub hover: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub book_move: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub panel: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub panel_text: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub clock_idle: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub clock_running: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub clock_flagged: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub cursor: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub move_input: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub move_input_invalid: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub annotation_green: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub annotation_red: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub annotation_yellow: [f32; 4],
+  #[serde(deserialize_with = "hex_color")] pub annotation_blue: [f32; 4]
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Theme {
+      dark_square: rgb_to_color!(161, 111, 92),
+      light_square: rgb_to_color!(236, 211, 186),
+      selected_square: rgb_to_color!(124, 166, 120),
+      last_move: rgb_to_color!(93, 142, 162),
+      check: rgb_to_color!(214, 76, 56),
+      premove: rgb_to_color!(140, 110, 170),
+      hint: rgb_to_color!(90, 170, 220),
+      legal_dark_square: rgb_to_color!(176, 39, 49),
+      legal_light_square: rgb_to_color!(222, 62, 77),
+      legal_move: [0.08, 0.33, 0.12, 0.5],
+      hover: [1.0, 1.0, 1.0, 0.6],
+      book_move: [0.16, 0.55, 0.27, 0.75],
+      panel: rgb_to_color!(48, 46, 43),
+      panel_text: rgb_to_color!(230, 230, 230),
+      clock_idle: rgb_to_color!(38, 36, 33),
+      clock_running: rgb_to_color!(98, 153, 36),
+      clock_flagged: rgb_to_color!(176, 39, 49),
+      cursor: rgb_to_color!(246, 190, 0),
+      move_input: rgb_to_color!(38, 36, 33),
+      move_input_invalid: rgb_to_color!(176, 39, 49),
+      annotation_green: [0.08, 0.47, 0.11, 0.8],
+      annotation_red: [0.53, 0.13, 0.13, 0.8],
+      annotation_yellow: [0.9, 0.68, 0.0, 0.8],
+      annotation_blue: [0.0, 0.19, 0.53, 0.8]
+    }
+  }
+}
+
+/* #rrggbb or #rrggbbaa */
+pub fn parse_color(s: &str) -> Option<[f32; 4]> {
+  let hex = s.strip_prefix('#')?;
+  if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) { return None; }
+
+  let mut color = [1.0; 4];
+
+  for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+    *channel = u8::from_str_radix(&hex[i * 2 .. i * 2 + 2], 16).ok()? as f32 / 255.0;
+  }
+
+  Some(color)
+}
+
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f32; 4], D::Error> {
+  let s = String::deserialize(deserializer)?;
+
+  parse_color(&s).ok_or_else(|| D::Error::custom(format!("{} isn't a #rrggbb or #rrggbbaa color", s)))
+}
+
+fn read_toml<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+  toml::from_str(&fs::read_to_string(path)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+impl Theme {
+  pub fn load(path: &Path) -> io::Result<Self> {
+    read_toml(path)
+  }
+
+  pub fn annotation(&self, color: MarkColor) -> [f32; 4] {
+    match color {
+      MarkColor::Green => self.annotation_green,
+      MarkColor::Red => self.annotation_red,
+      MarkColor::Yellow => self.annotation_yellow,
+      MarkColor::Blue => self.annotation_blue
+    }
+  }
+}
+
+/* fen letters of the pieces in the order the sprites are kept in, see Piece::sprite_sheet_pos */
+const SPRITE_ORDER: &str = "KQBNRPkqbnrp";
+
+/* describes a sprite sheet, a grid of equally sized pieces in any order, or one svg file per piece */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PieceSet {
+  /* png or svg, relative to the descriptor file, None for the sheet built into the game */
+  pub path: Option<PathBuf>,
+  /* used instead of path, {color} is w or b and {piece} one of KQBNRP, e.g. "cburnett/{color}{piece}.svg" */
+  pub files: Option<String>,
+  pub columns: u32,
+  pub rows: u32,
+  /* fen letters left to right, top to bottom, e.g. KQBNRPkqbnrp */
+  pub order: String
+}
+
+impl Default for PieceSet {
+  fn default() -> Self {
+    PieceSet {
+      path: None,
+      files: None,
+      columns: 6,
+      rows: 2,
+      order: String::from(SPRITE_ORDER)
+    }
+  }
+}
+
+impl PieceSet {
+  pub fn load(path: &Path) -> io::Result<Self> {
+    let mut set: PieceSet = read_toml(path)?;
+
+    if let Some(dir) = path.parent() {
+      set.path = set.path.map(|sheet| dir.join(sheet));
+      set.files = set.files.map(|files| dir.join(files).to_string_lossy().into_owned());
+    }
+
+    let cells = set.columns.saturating_mul(set.rows) as usize;
+
+    if set.order.chars().count() > cells || SPRITE_ORDER.chars().any(|c| !set.order.contains(c)) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "order needs all 12 pieces and has to fit in the grid"));
+    }
+
+    Ok(set)
+  }
+
+  /* the file of every piece, in sprite order */
+  pub fn piece_files(&self) -> Option<Vec<PathBuf>> {
+    let files = self.files.as_ref()?;
+
+    Some(SPRITE_ORDER.chars().map(|c| {
+      let color = if c.is_ascii_uppercase() { "w" } else { "b" };
+
+      PathBuf::from(files.replace("{color}", color).replace("{piece}", &c.to_ascii_uppercase().to_string()))
+    }).collect())
+  }
+
+  /* column and row of every sprite, in sprite order */
+  pub fn cells(&self) -> Vec<(u32, u32)> {
+    SPRITE_ORDER.chars().map(|c| {
+      let idx = self.order.chars().position(|o| o == c).unwrap() as u32;
+
+      (idx % self.columns, idx / self.columns)
+    }).collect()
+  }
+}
+
+/* the .toml files in a directory, sorted by name */
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+  let mut paths = fs::read_dir(dir).map(|entries| {
+    entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+      .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+      .collect::<Vec<_>>()
+  }).unwrap_or_default();
+
+  paths.sort();
+  paths
+}
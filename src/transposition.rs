@@ -0,0 +1,152 @@
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Bound {
+  Exact,
+  Lower,
+  Upper
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+  pub score: i32,
+  pub depth: u8,
+  pub bound: Bound,
+  pub best: Option<(usize, usize)>
+}
+
+/*
+  lock-free table shared between the search threads, every slot stores
+  key ^ data next to data so a torn write from two threads just reads as a miss
+*/
+struct Slot {
+  key: AtomicU64,
+  data: AtomicU64
+}
+
+pub struct TranspositionTable {
+  slots: Vec<Slot>
+}
+
+impl Entry {
+  fn pack(self) -> u64 {
+    let bound = match self.bound {
+      Bound::Exact => 0,
+      Bound::Lower => 1,
+      Bound::Upper => 2
+    };
+
+    let mut data = (self.score as i16 as u16) as u64;
+    data |= (self.depth as u64) << 16;
+    data |= bound << 24;
+
+    if let Some((start, target)) = self.best {
+      data |= 1 << 26;
+      data |= (start as u64) << 27;
+      data |= (target as u64) << 33;
+    }
+
+    data
+  }
+
+  fn unpack(data: u64) -> Self {
+    let bound = match (data >> 24) & 0b11 {
+      0 => Bound::Exact,
+      1 => Bound::Lower,
+      _ => Bound::Upper
+    };
+
+    let best = if data & (1 << 26) != 0 {
+      Some((((data >> 27) & 63) as usize, ((data >> 33) & 63) as usize))
+    } else {
+      None
+    };
+
+    Entry {
+      score: data as u16 as i16 as i32,
+      depth: (data >> 16) as u8,
+      bound,
+      best
+    }
+  }
+}
+
+impl TranspositionTable {
+  pub fn new(size_mb: usize) -> Self {
+    let count = size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<Slot>();
+    let len = 1 << count.ilog2();
+
+    TranspositionTable {
+      slots: (0 .. len).map(|_| Slot { key: AtomicU64::new(0), data: AtomicU64::new(0) }).collect()
+    }
+  }
+
+  fn slot(&self, key: u64) -> &Slot {
+    &self.slots[key as usize & (self.slots.len() - 1)]
+  }
+
+  pub fn probe(&self, key: u64) -> Option<Entry> {
+    let slot = self.slot(key);
+    let data = slot.data.load(Ordering::Relaxed);
+
+    if data == 0 || slot.key.load(Ordering::Relaxed) ^ data != key {
+      return None;
+    }
+
+    Some(Entry::unpack(data))
+  }
+
+  pub fn store(&self, key: u64, entry: Entry) {
+    let slot = self.slot(key);
+    let data = entry.pack();
+
+    slot.key.store(key ^ data, Ordering::Relaxed);
+    slot.data.store(data, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn stores_and_probes_entries() {
+    let tt = TranspositionTable::new(1);
+    let (key, other) = (0x1234_5678_9abc_def0, 0x0fed_cba9_8765_4321);
+
+    tt.store(key, Entry { score: -250, depth: 7, bound: Bound::Lower, best: Some((52, 36)) });
+    tt.store(other, Entry { score: 29990, depth: 1, bound: Bound::Upper, best: None });
+
+    let entry = tt.probe(key).unwrap();
+    assert_eq!((entry.score, entry.depth, entry.bound, entry.best), (-250, 7, Bound::Lower, Some((52, 36))));
+
+    let entry = tt.probe(other).unwrap();
+    assert_eq!((entry.score, entry.depth, entry.bound, entry.best), (29990, 1, Bound::Upper, None));
+  }
+
+  #[test]
+  fn misses_keys_that_share_a_slot() {
+    let tt = TranspositionTable::new(1);
+    let key = 0x1234_5678_9abc_def0;
+    let len = tt.slots.len() as u64;
+
+    tt.store(key, Entry { score: 10, depth: 3, bound: Bound::Exact, best: None });
+
+    /* same index, different key */
+    assert!(tt.probe(key ^ (len << 4)).is_none());
+    assert!(tt.probe(key + len).is_none());
+    assert!(tt.probe(key).is_some());
+  }
+
+  #[test]
+  fn misses_torn_writes() {
+    let tt = TranspositionTable::new(1);
+    let key = 0x1234_5678_9abc_def0;
+
+    tt.store(key, Entry { score: 10, depth: 3, bound: Bound::Exact, best: None });
+
+    /* data from another store landing without its key */
+    tt.slot(key).data.store(Entry { score: 20, depth: 4, bound: Bound::Lower, best: None }.pack(), Ordering::Relaxed);
+    assert!(tt.probe(key).is_none());
+  }
+}
@@ -0,0 +1,458 @@
+use std::io::{ self, Write };
+use std::time::{ Duration, Instant };
+
+use crossterm::{ cursor, execute, queue, terminal };
+use crossterm::event::{ self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers };
+use crossterm::style::{ self, Stylize };
+
+use crate::annotation::Annotations;
+use crate::board::{ square_name, Board, GameResult };
+use crate::clock::{ self, Clock, TimeControl };
+use crate::engine::Engine;
+use crate::move_generator::{ is_in_check, legal_moves, Move };
+use crate::notation;
+use crate::pgn::Game;
+use crate::piece::{ Color, Piece, PieceType };
+use crate::theme::Theme;
+
+const HELP: &str = "arrows and enter move pieces, or type e4, Nf3 or e2e4 | ctrl+z undo | ctrl+f flip | ctrl+c quit";
+
+/* the game in a terminal for machines without a display, same rules and engine as the gui */
+pub struct Tui {
+  pub board: Board,
+  pub history: Vec<Board>,
+  pub result: Option<GameResult>,
+  pub engine: Engine,
+  pub flipped: bool,
+  clock: Option<Clock>,
+  pub theme: Theme,
+  cursor: usize,
+  selected: Option<usize>,
+  input: String,
+  message: String,
+  quit: bool
+}
+
+/* puts the terminal back however run ends */
+struct RawMode;
+
+impl RawMode {
+  fn enter() -> io::Result<Self> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    Ok(RawMode)
+  }
+}
+
+impl Drop for RawMode {
+  fn drop(&mut self) {
+    let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+  }
+}
+
+fn rgb(color: [f32; 4]) -> style::Color {
+  let channel = |c: f32| (c * 255.0).round() as u8;
+
+  style::Color::Rgb { r: channel(color[0]), g: channel(color[1]), b: channel(color[2]) }
+}
+
+/* the filled glyphs for both sides, the foreground color tells them apart */
+fn glyph(piece: Piece) -> char {
+  match piece.piece {
+    PieceType::King => '♚',
+    PieceType::Queen => '♛',
+    PieceType::Rook => '♜',
+    PieceType::Bishop => '♝',
+    PieceType::Knight => '♞',
+    PieceType::Pawn => '♟'
+  }
+}
+
+fn color_name(color: Color) -> &'static str {
+  if color == Color::White { "White" } else { "Black" }
+}
+
+impl Default for Tui {
+  fn default() -> Self {
+    Tui::new()
+  }
+}
+
+impl Tui {
+  pub fn new() -> Self {
+    let board = Board::new();
+
+    Tui {
+      history: vec![board.clone()],
+      board,
+      result: None,
+      engine: Engine::new(),
+      flipped: false,
+      clock: None,
+      theme: Theme::default(),
+      /* e2, where the gui's keyboard cursor starts too */
+      cursor: 38,
+      selected: None,
+      input: String::new(),
+      message: String::new(),
+      quit: false
+    }
+  }
+
+  /* the moves played so far, the terminal has no arrows or marks */
+  pub fn game(&self) -> Game {
+    Game { positions: self.history.clone(), annotations: vec![Annotations::default(); self.history.len()], result: self.result }
+  }
+
+  /* carries on from the game's last position */
+  pub fn load_game(&mut self, game: Game) {
+    self.engine.stop();
+    self.history = game.positions;
+    self.board = self.history[self.history.len() - 1].clone();
+    self.result = game.result.or_else(|| self.final_result());
+    self.selected = None;
+
+    if let Some(clock) = &mut self.clock {
+      clock.set_turn(self.board.color_to_move);
+    }
+  }
+
+  /* the engine plays color on its own, the board turns so the human side is at the bottom */
+  pub fn set_engine_color(&mut self, color: Option<Color>) {
+    self.engine.color = color;
+    self.flipped = color == Some(Color::White);
+    self.cursor = if self.flipped { 63 - 38 } else { 38 };
+  }
+
+  /* starts a new clock for the side to move, None plays untimed */
+  pub fn set_time_control(&mut self, control: Option<TimeControl>) {
+    self.clock = control.map(|control| Clock::new(control, self.board.color_to_move));
+  }
+
+  pub fn run(&mut self) -> io::Result<()> {
+    let _raw = RawMode::enter()?;
+    let mut stdout = io::stdout();
+    let mut last = Instant::now();
+
+    while !self.quit {
+      self.draw(&mut stdout)?;
+
+      /* wakes up often enough for the clock and the engine even without keys */
+      if event::poll(Duration::from_millis(100))? {
+        if let Event::Key(key) = event::read()? {
+          if key.kind == KeyEventKind::Press {
+            self.key(key);
+          }
+        }
+      }
+
+      let now = Instant::now();
+      self.update(now - last);
+      last = now;
+    }
+
+    self.engine.stop();
+
+    Ok(())
+  }
+
+  fn key(&mut self, key: KeyEvent) {
+    log::trace!(target: "input", "{:?} pressed", key.code);
+    self.message.clear();
+
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+      match key.code {
+        KeyCode::Char('c') => self.quit = true,
+        KeyCode::Char('z') => self.undo(),
+        KeyCode::Char('f') => self.flipped = !self.flipped,
+        _ => {}
+      }
+
+      return;
+    }
+
+    match key.code {
+      KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right => self.move_cursor(key.code),
+      KeyCode::Enter if self.input.is_empty() => self.click(),
+      KeyCode::Enter => self.submit_input(),
+      KeyCode::Backspace => { self.input.pop(); }
+
+      KeyCode::Esc => {
+        self.input.clear();
+        self.selected = None;
+      }
+
+      KeyCode::Char(c) if !c.is_whitespace() && self.input.len() < 8 => self.input.push(c),
+      _ => {}
+    }
+  }
+
+  /* the arrows move on screen, so up is always away from the side at the bottom */
+  fn move_cursor(&mut self, key: KeyCode) {
+    let flip = |idx: usize| if self.flipped { 63 - idx } else { idx };
+    let (x, y) = (flip(self.cursor) / 8, flip(self.cursor) % 8);
+
+    let (x, y) = match key {
+      KeyCode::Left => (x.saturating_sub(1), y),
+      KeyCode::Right => ((x + 1).min(7), y),
+      KeyCode::Up => (x, y.saturating_sub(1)),
+      KeyCode::Down => (x, (y + 1).min(7)),
+      _ => (x, y)
+    };
+
+    self.cursor = flip(x * 8 + y);
+  }
+
+  fn can_move(&mut self) -> bool {
+    if self.result.is_some() {
+      self.message = String::from("the game is over, ctrl+z takes moves back");
+      return false;
+    }
+
+    if self.engine_to_move() {
+      self.message = String::from("the engine is thinking");
+      return false;
+    }
+
+    true
+  }
+
+  /* picks up the piece under the cursor, or puts the picked up one down there */
+  fn click(&mut self) {
+    if !self.can_move() { return; }
+
+    let own_piece = self.board.get_piece_at(self.cursor).is_some_and(|piece| piece.color == self.board.color_to_move);
+
+    match self.selected {
+      Some(selected) if selected == self.cursor => self.selected = None,
+
+      Some(selected) => {
+        match legal_moves(&self.board).into_iter().find(|mov| mov.start == selected && mov.target == self.cursor) {
+          Some(mov) => self.play_move(mov),
+          None if own_piece => self.selected = Some(self.cursor),
+          None => self.message = format!("{} to {} isn't legal", square_name(selected), square_name(self.cursor))
+        }
+      }
+
+      None if own_piece => self.selected = Some(self.cursor),
+      None => {}
+    }
+  }
+
+  fn submit_input(&mut self) {
+    let text = std::mem::take(&mut self.input);
+
+    if !self.can_move() { return; }
+
+    match notation::parse_move(&self.board, &text) {
+      Some(mov) => self.play_move(mov),
+      None => {
+        log::debug!(target: "input", "'{}' isn't a legal move", text);
+        self.message = format!("'{}' isn't a legal move here", text);
+      }
+    }
+  }
+
+  /* takes back to the human's last turn, so two plies against the engine */
+  fn undo(&mut self) {
+    self.engine.stop();
+
+    let plies = if self.engine.color.is_some() && !self.engine_to_move() { 2 } else { 1 };
+
+    if self.history.len() <= plies {
+      self.message = String::from("nothing to take back");
+      return;
+    }
+
+    self.history.truncate(self.history.len() - plies);
+    self.board = self.history[self.history.len() - 1].clone();
+    self.result = None;
+    self.selected = None;
+
+    if let Some(clock) = &mut self.clock {
+      clock.set_turn(self.board.color_to_move);
+    }
+  }
+
+  fn engine_to_move(&self) -> bool {
+    self.engine.to_move(&self.board)
+  }
+
+  fn update(&mut self, dt: Duration) {
+    if self.result.is_some() { return; }
+
+    if let Some(clock) = &mut self.clock {
+      clock.tick(dt);
+      self.result = clock.timeout_result(&self.board);
+
+      if let (Some(result), Some(color)) = (self.result, clock.flagged) {
+        log::info!(target: "rules", "{:?} ran out of time, {:?}", color, result);
+        self.engine.stop();
+        return;
+      }
+    }
+
+    if let Some(mov) = self.engine.finished_move(&self.board) {
+      self.play_move(mov);
+    }
+
+    /* book moves are played straight away */
+    if self.engine_to_move() && self.result.is_none() {
+      if let Some(mov) = self.engine.think(&self.board) {
+        self.play_move(mov);
+      }
+    }
+  }
+
+  /* checkmate or stalemate, the clock is checked in update */
+  fn final_result(&self) -> Option<GameResult> {
+    if !legal_moves(&self.board).is_empty() { return None; }
+
+    let mut winner = self.board.color_to_move;
+    winner.reverse();
+
+    Some(if is_in_check(&self.board, self.board.color_to_move) { GameResult::Win(winner) } else { GameResult::Draw })
+  }
+
+  fn play_move(&mut self, mov: Move) {
+    log::info!(target: "rules", "{} played", notation::to_san(&self.board, mov));
+
+    self.board.make_move(mov);
+    self.history.push(self.board.clone());
+    self.selected = None;
+    self.result = self.final_result();
+
+    if let Some(result) = self.result {
+      log::info!(target: "rules", "game over, {:?}", result);
+    }
+
+    if let Some(clock) = &mut self.clock {
+      clock.press();
+    }
+  }
+
+  fn status(&self) -> String {
+    let to_move = self.board.color_to_move;
+
+    match self.result {
+      Some(GameResult::Win(color)) => format!("{} wins", color_name(color)),
+      Some(GameResult::Draw) => String::from("Draw"),
+      None if self.engine.is_thinking() => format!("{} to move, thinking...", color_name(to_move)),
+      None if is_in_check(&self.board, to_move) => format!("{} to move, check", color_name(to_move)),
+      None => format!("{} to move", color_name(to_move))
+    }
+  }
+
+  /* "12. Nf3 Nc6" lines, numbered the way pgn does it */
+  fn move_list(&self) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for (before, after) in self.history.iter().zip(self.history.iter().skip(1)) {
+      let san = match after.last_move {
+        Some(mov) => notation::to_san(before, mov),
+        None => continue
+      };
+
+      let number = before.ply / 2 + 1;
+
+      match lines.last_mut() {
+        Some(line) if before.color_to_move == Color::Black && line.starts_with(&format!("{}.", number)) => {
+          line.push(' ');
+          line.push_str(&san);
+        }
+
+        _ if before.color_to_move == Color::Black => lines.push(format!("{}. ... {}", number, san)),
+        _ => lines.push(format!("{}. {}", number, san))
+      }
+    }
+
+    lines
+  }
+
+  fn square(&self, idx: usize, legal_targets: &[usize]) -> String {
+    let is_light_square = (idx / 8 + idx % 8).is_multiple_of(2);
+    let last_move = self.board.last_move.is_some_and(|mov| mov.start == idx || mov.target == idx);
+    let piece = self.board.get_piece_at(idx);
+    let in_check = piece.is_some_and(|piece| {
+      piece.piece == PieceType::King && piece.color == self.board.color_to_move && is_in_check(&self.board, piece.color)
+    });
+
+    let background = if self.selected == Some(idx) {
+      self.theme.selected_square
+    } else if in_check {
+      self.theme.check
+    } else if last_move {
+      self.theme.last_move
+    } else if is_light_square {
+      self.theme.light_square
+    } else {
+      self.theme.dark_square
+    };
+
+    let center = match piece {
+      Some(piece) => glyph(piece),
+      None if legal_targets.contains(&idx) => '•',
+      None => ' '
+    };
+
+    let (left, right) = if self.cursor == idx { ('[', ']') } else { (' ', ' ') };
+    let foreground = match piece {
+      Some(piece) if piece.color == Color::White => style::Color::White,
+      Some(_) => style::Color::Black,
+      None => rgb(self.theme.legal_move)
+    };
+
+    format!("{}{}{}", left, center, right).with(foreground).on(rgb(background)).to_string()
+  }
+
+  fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+    let flip = |idx: usize| if self.flipped { 63 - idx } else { idx };
+    let legal_targets = match self.selected {
+      Some(selected) => legal_moves(&self.board).into_iter().filter(|mov| mov.start == selected).map(|mov| mov.target).collect(),
+      None => Vec::new()
+    };
+
+    let mut panel = vec![self.status(), String::new()];
+
+    if let Some(clock) = &self.clock {
+      for color in [Color::White, Color::Black] {
+        let running = clock.turn == color && self.result.is_none();
+        panel.push(format!("{} {} {}", if running { ">" } else { " " }, color_name(color), clock::format_time(clock.remaining(color))));
+      }
+
+      panel.push(String::new());
+    }
+
+    /* the latest moves fill the rest of the panel */
+    let moves = self.move_list();
+    let room = 9usize.saturating_sub(panel.len());
+    panel.extend(moves[moves.len().saturating_sub(room) ..].iter().cloned());
+
+    let mut lines = Vec::new();
+
+    for row in 0 .. 8 {
+      let rank = &square_name(flip(row))[1 ..];
+      let squares = (0 .. 8).map(|column| self.square(flip(column * 8 + row), &legal_targets)).collect::<String>();
+
+      lines.push(format!(" {} {}   {}", rank, squares, panel.get(row).map_or("", String::as_str)));
+    }
+
+    let files = (0 .. 8).map(|column| format!(" {} ", &square_name(flip(column * 8))[.. 1])).collect::<String>();
+    lines.push(format!("   {}   {}", files, panel.get(8).map_or("", String::as_str)));
+    lines.push(String::new());
+    lines.push(format!(" > {}_", self.input));
+    lines.push(format!(" {}", self.message));
+    lines.push(format!(" {}", HELP));
+
+    queue!(out, cursor::MoveTo(0, 0))?;
+
+    for line in lines {
+      queue!(out, style::Print(line), terminal::Clear(terminal::ClearType::UntilNewLine), cursor::MoveToNextLine(1))?;
+    }
+
+    queue!(out, terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    out.flush()
+  }
+}
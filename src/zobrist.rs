@@ -0,0 +1,72 @@
+use crate::board::Board;
+use crate::piece::Color;
+
+/* internal hash keys for the search, these have nothing to do with polyglot keys */
+const PIECE_KEYS: [[u64; 8 * 8]; 12] = compile_piece_keys();
+const EN_PASSANT_KEYS: [u64; 8 * 8] = compile_en_passant_keys();
+const BLACK_TO_MOVE: u64 = splitmix64(0x2545f4914f6cdd1d).1;
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+  let state = state.wrapping_add(0x9e3779b97f4a7c15);
+  let mut z = state;
+
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+  (state, z ^ (z >> 31))
+}
+
+const fn compile_piece_keys() -> [[u64; 8 * 8]; 12] {
+  let mut buf = [[0; 8 * 8]; 12];
+  let mut state = 0;
+
+  let mut i = 0;
+  while i < 12 {
+    let mut j = 0;
+    while j < 64 {
+      let (next, key) = splitmix64(state);
+      state = next;
+      buf[i][j] = key;
+      j += 1;
+    }
+
+    i += 1;
+  }
+
+  buf
+}
+
+const fn compile_en_passant_keys() -> [u64; 8 * 8] {
+  let mut buf = [0; 8 * 8];
+  let mut state = 0xdeadbeef;
+
+  let mut i = 0;
+  while i < 64 {
+    let (next, key) = splitmix64(state);
+    state = next;
+    buf[i] = key;
+    i += 1;
+  }
+
+  buf
+}
+
+pub fn hash(board: &Board) -> u64 {
+  let mut key = 0;
+
+  for (idx, tile) in board.board.iter().enumerate() {
+    if let Some(piece) = tile.piece {
+      key ^= PIECE_KEYS[piece.sprite_sheet_pos()][idx];
+    }
+  }
+
+  if let Some(square) = board.en_passant_square() {
+    key ^= EN_PASSANT_KEYS[square];
+  }
+
+  if board.color_to_move == Color::Black {
+    key ^= BLACK_TO_MOVE;
+  }
+
+  key
+}